@@ -0,0 +1,90 @@
+//! Generates a dungeon level exactly as the game would for a seed and writes
+//! it out as text, JSON and a PNG image, to attach to design reviews or to
+//! compare maps between versions.
+//!
+//! `cargo run --bin map_export -- [--seed S] [--depth D] [--map-size WxH] [--out DIR] [--tile-size PX]`
+
+use dungeoncrawl::prelude::*;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+/// Finds the value following a command line flag, such as the `42` in `--seed 42`.
+/// * `args` - the command line arguments
+/// * `flag` - the flag to look for
+fn arg_value(args: &[String], flag: &str) -> Option<String> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|i| args.get(i + 1).cloned())
+}
+
+/// Writes one of the exported files, stopping the tool if it can't.
+/// * `path` - the file to write
+/// * `contents` - the text to write to it
+fn write_file(path: &Path, contents: &str) {
+    fs::write(path, contents).unwrap_or_else(|e| {
+        eprintln!("{}: {}", path.display(), e);
+        process::exit(1);
+    });
+    println!("Wrote {}", path.display());
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let game_rng = match arg_value(&args, "--seed") {
+        Some(seed) => GameRng::new(parse_seed(&seed)),
+        None => GameRng::from_entropy(),
+    };
+    let depth: u32 = arg_value(&args, "--depth").map_or(0, |n| n.parse().expect("Invalid --depth"));
+    let tile_size: u32 =
+        arg_value(&args, "--tile-size").map_or(32, |n| n.parse().expect("Invalid --tile-size"));
+    let out = arg_value(&args, "--out").unwrap_or_else(|| ".".to_string());
+    let size = arg_value(&args, "--map-size").map_or((SCREEN_WIDTH, SCREEN_HEIGHT), |s| {
+        let (w, h) = s.split_once('x').expect("--map-size must look like 80x50");
+        (
+            w.parse().expect("Invalid --map-size width"),
+            h.parse().expect("Invalid --map-size height"),
+        )
+    });
+    if size.0 < MIN_MAP_SIZE || size.1 < MIN_MAP_SIZE {
+        eprintln!("Maps must be at least {0}x{0} tiles", MIN_MAP_SIZE);
+        process::exit(1);
+    }
+    if tile_size == 0 {
+        eprintln!("--tile-size must be at least 1");
+        process::exit(1);
+    }
+
+    let data = GameData::load().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+    let mb = MapBuilder::new(
+        &mut game_rng.map_stream(depth),
+        size.0,
+        size.1,
+        depth,
+        &data,
+    );
+    let export = MapExport::new(&mb, depth, &data.templates);
+
+    fs::create_dir_all(&out).unwrap_or_else(|e| {
+        eprintln!("{}: {}", out, e);
+        process::exit(1);
+    });
+    let name = format!("seed{}_level{}", game_rng.seed, depth + 1);
+    let path = Path::new(&out).join(&name);
+    write_file(&path.with_extension("txt"), &export.text());
+    write_file(&path.with_extension("json"), &export.json());
+    let image = export.png(FONT_FILE, tile_size).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+    let png = path.with_extension("png");
+    image.save(&png).unwrap_or_else(|e| {
+        eprintln!("{}: {}", png.display(), e);
+        process::exit(1);
+    });
+    println!("Wrote {}", png.display());
+}
@@ -0,0 +1,275 @@
+//! Generates many maps with every architect and reports what they look like,
+//! along with any that panic or never finish, so map generation regressions
+//! are caught before release.
+//!
+//! `cargo run --release --bin mapgen_stats -- [--maps N] [--map-size WxH] [--depth D] [--seed S] [--timeout SECS]`
+
+use dungeoncrawl::prelude::*;
+use std::env;
+use std::panic::{self, AssertUnwindSafe};
+use std::process;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// What one generated map looked like.
+struct MapStats {
+    seed: u64,
+    /// The share of the map that is floor, in percent.
+    floor_percent: f32,
+    /// The number of separate areas of floor, which should always be 1.
+    areas: usize,
+    /// The number of steps from the player start to the exit.
+    path_length: f32,
+    spawns: usize,
+    vaults: usize,
+    time: Duration,
+}
+
+/// Why a map could not be generated.
+enum Failure {
+    Panicked(String),
+    TimedOut,
+}
+
+/// Finds the value following a command line flag, such as the `30` in `--maps 30`.
+/// * `args` - the command line arguments
+/// * `flag` - the flag to look for
+fn arg_value(args: &[String], flag: &str) -> Option<String> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|i| args.get(i + 1).cloned())
+}
+
+/// Gives the game data a dungeon plan that builds every level with one architect.
+/// * `data` - the game data as loaded
+/// * `architect` - one of `ARCHITECT_NAMES`
+fn with_architect(data: &GameData, architect: &str) -> GameData {
+    let level = LevelPlan {
+        architects: vec![architect.to_string()],
+        ..data.plan.levels[0].clone()
+    };
+    let mut data = data.clone();
+    data.plan = DungeonPlan {
+        levels: vec![level; FINAL_LEVEL as usize + 1],
+    };
+    data
+}
+
+/// Counts the separate areas of floor on a map, counting doors as floor.
+/// * `map` - the generated map
+fn count_areas(map: &Map) -> usize {
+    let mut seen = vec![false; map.tiles.len()];
+    let mut areas = 0;
+    for first in 0..map.tiles.len() {
+        if !map.tiles[first].is_walkable() || seen[first] {
+            continue;
+        }
+        areas += 1;
+        seen[first] = true;
+        let mut pending = vec![first];
+        while let Some(idx) = pending.pop() {
+            let pt = map.index_to_point2d(idx);
+            for (dx, dy) in [(0, -1), (1, 0), (0, 1), (-1, 0)] {
+                if let Some(next) = map.try_idx(Point::new(pt.x + dx, pt.y + dy)) {
+                    if map.tiles[next].is_walkable() && !seen[next] {
+                        seen[next] = true;
+                        pending.push(next);
+                    }
+                }
+            }
+        }
+    }
+    areas
+}
+
+/// Generates one map and measures it.
+/// * `data` - the game data, planned for a single architect
+/// * `seed` - the map generation seed
+/// * `size` - the width and height of the map
+/// * `depth` - the dungeon level, starting at 0
+fn measure(data: &GameData, seed: u64, size: (i32, i32), depth: u32) -> MapStats {
+    let mut rng = RandomNumberGenerator::seeded(seed);
+    let start = Instant::now();
+    let mb = MapBuilder::new(&mut rng, size.0, size.1, depth, data);
+    let time = start.elapsed();
+
+    let floor = mb
+        .map
+        .tiles
+        .iter()
+        .filter(|t| **t == TileType::Floor)
+        .count();
+    let dijkstra_map = DijkstraMap::new(
+        mb.map.width,
+        mb.map.height,
+        &[mb.map.point2d_to_index(mb.player_start)],
+        &mb.map,
+        mb.map.tiles.len() as f32,
+    );
+    MapStats {
+        seed,
+        floor_percent: floor as f32 * 100.0 / mb.map.tiles.len() as f32,
+        areas: count_areas(&mb.map),
+        path_length: dijkstra_map.map[mb.map.point2d_to_index(mb.amulet_start)],
+        spawns: mb.monster_spawns.len() + mb.entity_spawns.len(),
+        vaults: mb.placed_vaults.len(),
+        time,
+    }
+}
+
+/// Generates a map on another thread, so a panic or a map that never
+/// finishes is reported rather than stopping the tool.
+/// * `data` - the game data, planned for a single architect
+/// * `seed` - the map generation seed
+/// * `size` - the width and height of the map
+/// * `depth` - the dungeon level, starting at 0
+/// * `timeout` - how long to wait before giving up on the map
+fn try_measure(
+    data: &Arc<GameData>,
+    seed: u64,
+    size: (i32, i32),
+    depth: u32,
+    timeout: Duration,
+) -> Result<MapStats, Failure> {
+    let (sender, receiver) = mpsc::channel();
+    let data = Arc::clone(data);
+    thread::spawn(move || {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| measure(&data, seed, size, depth)));
+        // the receiver is gone if the map took too long, which is already reported
+        let _ = sender.send(result);
+    });
+    match receiver.recv_timeout(timeout) {
+        Ok(Ok(stats)) => Ok(stats),
+        Ok(Err(payload)) => {
+            let message = payload
+                .downcast_ref::<String>()
+                .cloned()
+                .or_else(|| payload.downcast_ref::<&str>().map(|s| s.to_string()))
+                .unwrap_or_else(|| "unknown panic".to_string());
+            Err(Failure::Panicked(message))
+        }
+        // a thread stuck in a loop can't be stopped, so it is left to run
+        Err(_) => Err(Failure::TimedOut),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let maps: u64 = arg_value(&args, "--maps").map_or(100, |n| n.parse().expect("Invalid --maps"));
+    let first_seed: u64 =
+        arg_value(&args, "--seed").map_or(0, |n| n.parse().expect("Invalid --seed"));
+    let depth: u32 = arg_value(&args, "--depth").map_or(0, |n| n.parse().expect("Invalid --depth"));
+    let timeout = Duration::from_secs(
+        arg_value(&args, "--timeout").map_or(10, |n| n.parse().expect("Invalid --timeout")),
+    );
+    let size = arg_value(&args, "--map-size").map_or((SCREEN_WIDTH, SCREEN_HEIGHT), |s| {
+        let (w, h) = s.split_once('x').expect("--map-size must look like 80x50");
+        (
+            w.parse().expect("Invalid --map-size width"),
+            h.parse().expect("Invalid --map-size height"),
+        )
+    });
+    if maps == 0 {
+        eprintln!("--maps must be at least 1");
+        process::exit(1);
+    }
+    if size.0 < MIN_MAP_SIZE || size.1 < MIN_MAP_SIZE {
+        eprintln!("Maps must be at least {0}x{0} tiles", MIN_MAP_SIZE);
+        process::exit(1);
+    }
+
+    let data = GameData::load().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+    // panics are reported in the table, not as they happen
+    panic::set_hook(Box::new(|_| {}));
+
+    println!(
+        "{} maps of {}x{} per architect on dungeon level {}, seeds {} to {}\n",
+        maps,
+        size.0,
+        size.1,
+        depth + 1,
+        first_seed,
+        first_seed + maps - 1
+    );
+    println!(
+        "{:<22} {:>7} {:>7} {:>6} {:>6} {:>7} {:>7} {:>9} {:>9} {:>6}",
+        "Architect",
+        "Floor%",
+        "Min%",
+        "Areas",
+        "Path",
+        "Spawns",
+        "Vaults",
+        "Avg ms",
+        "Max ms",
+        "Fails"
+    );
+    let mut failures = Vec::new();
+    for architect in ARCHITECT_NAMES {
+        let data = Arc::new(with_architect(&data, architect));
+        let mut results = Vec::new();
+        for seed in first_seed..first_seed + maps {
+            match try_measure(&data, seed, size, depth, timeout) {
+                Ok(stats) => results.push(stats),
+                Err(Failure::Panicked(message)) => failures.push(format!(
+                    "{} seed {}: panicked: {}",
+                    architect, seed, message
+                )),
+                Err(Failure::TimedOut) => {
+                    failures.push(format!(
+                        "{} seed {}: still running after {:?}, skipping the rest",
+                        architect, seed, timeout
+                    ));
+                    break;
+                }
+            }
+        }
+
+        let count = results.len().max(1) as f32;
+        let average =
+            |value: &dyn Fn(&MapStats) -> f32| results.iter().map(value).sum::<f32>() / count;
+        let min_floor = results
+            .iter()
+            .map(|s| s.floor_percent)
+            .fold(f32::MAX, f32::min);
+        let max_ms = results
+            .iter()
+            .map(|s| s.time.as_secs_f32() * 1000.0)
+            .fold(0.0, f32::max);
+        println!(
+            "{:<22} {:>7.1} {:>7.1} {:>6.2} {:>6.1} {:>7.1} {:>7.2} {:>9.2} {:>9.2} {:>6}",
+            architect,
+            average(&|s| s.floor_percent),
+            if results.is_empty() { 0.0 } else { min_floor },
+            average(&|s| s.areas as f32),
+            average(&|s| s.path_length),
+            average(&|s| s.spawns as f32),
+            average(&|s| s.vaults as f32),
+            average(&|s| s.time.as_secs_f32() * 1000.0),
+            max_ms,
+            maps as usize - results.len()
+        );
+        results
+            .iter()
+            .filter(|stats| stats.areas != 1)
+            .for_each(|stats| {
+                failures.push(format!(
+                    "{} seed {}: {} separate areas of floor",
+                    architect, stats.seed, stats.areas
+                ))
+            });
+    }
+
+    println!();
+    failures.iter().for_each(|failure| println!("{}", failure));
+    println!("{} problems", failures.len());
+    // fail the run, so the tool can guard releases in CI
+    if !failures.is_empty() {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,133 @@
+//! Lints an entity template file and prints the spawn table for every dungeon
+//! level, so designers can check their changes without playing the game.
+//!
+//! `cargo run --bin template_report -- [FILE] [--spawns N]`
+
+use dungeoncrawl::prelude::*;
+use std::env;
+use std::fs;
+use std::process;
+
+/// The number of spawn points on a screen-sized map, see `MapBuilder`.
+const DEFAULT_SPAWNS: f32 = 50.0;
+
+/// Finds the value following a command line flag, such as the `30` in `--spawns 30`.
+/// * `args` - the command line arguments
+/// * `flag` - the flag to look for
+fn arg_value(args: &[String], flag: &str) -> Option<String> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|i| args.get(i + 1).cloned())
+}
+
+/// Prints the chance of each template spawning on a level, and what the
+/// monsters add up to over a typical number of spawn points.
+/// * `templates` - the templates to report on
+/// * `level` - the dungeon level, starting at 0
+/// * `spawns` - the number of spawn points on the level
+/// * `warnings` - collects anything that looks like a mistake
+fn report_level(templates: &Templates, level: usize, spawns: f32, warnings: &mut Vec<String>) {
+    println!("Dungeon level {}", level + 1);
+    let available: Vec<&Template> = templates
+        .available(level)
+        .filter(|t| t.frequency > 0)
+        .collect();
+    let total: i32 = available.iter().map(|t| t.frequency).sum();
+    if total == 0 {
+        println!("  nothing spawns here\n");
+        warnings.push(format!("nothing spawns on dungeon level {}", level + 1));
+        return;
+    }
+
+    println!(
+        "  {:<24} {:<6} {:>7} {:>5} {:>7}",
+        "Template", "Type", "Chance", "HP", "Damage"
+    );
+    let (mut enemy_chance, mut hp, mut damage) = (0.0, 0.0, 0.0);
+    available.iter().for_each(|t| {
+        let chance = t.frequency as f32 / total as f32;
+        let show = |value: Option<i32>| value.map_or("-".to_string(), |v| v.to_string());
+        println!(
+            "  {:<24} {:<6} {:>6.1}% {:>5} {:>7}",
+            t.name,
+            format!("{:?}", t.entity_type),
+            chance * 100.0,
+            show(t.hp),
+            show(t.base_damage)
+        );
+        if t.entity_type == EntityType::Enemy {
+            enemy_chance += chance;
+            hp += chance * t.hp.unwrap_or(0) as f32;
+            damage += chance * t.base_damage.unwrap_or(0) as f32;
+        }
+    });
+    println!(
+        "  Over {} spawns expect {:.1} monsters with {:.1} hp and {:.1} damage in total\n",
+        spawns,
+        enemy_chance * spawns,
+        hp * spawns,
+        damage * spawns
+    );
+    if enemy_chance == 0.0 {
+        warnings.push(format!("no monsters spawn on dungeon level {}", level + 1));
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let path = match args.first() {
+        Some(arg) if !arg.starts_with("--") => arg.clone(),
+        _ => TEMPLATE_FILE.to_string(),
+    };
+    let spawns = arg_value(&args, "--spawns")
+        .map_or(DEFAULT_SPAWNS, |n| n.parse().expect("Invalid --spawns"));
+
+    let text = fs::read_to_string(&path).unwrap_or_else(|e| {
+        eprintln!("{}: {}", path, e);
+        process::exit(1);
+    });
+    // parse without validating, so every problem is reported along with the table
+    let templates = Templates::parse_unvalidated(&text, &path).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+    let issues = templates.validate(&text);
+
+    let mut warnings = Vec::new();
+    (0..=FINAL_LEVEL as usize).for_each(|level| {
+        report_level(&templates, level, spawns, &mut warnings);
+    });
+    templates
+        .entities
+        .iter()
+        .filter(|t| !t.levels.is_empty() && t.levels.iter().all(|l| *l > FINAL_LEVEL as usize))
+        .for_each(|t| {
+            warnings.push(format!(
+                "{} only spawns below the last dungeon level, {}",
+                t.name,
+                FINAL_LEVEL + 1
+            ))
+        });
+    templates
+        .entities
+        .iter()
+        .filter(|t| t.entity_type == EntityType::Enemy && t.base_damage.is_none())
+        .for_each(|t| warnings.push(format!("{} has no base_damage, so it can't hurt", t.name)));
+
+    issues
+        .iter()
+        .for_each(|issue| println!("{}: error: {}", path, issue));
+    warnings
+        .iter()
+        .for_each(|warning| println!("{}: warning: {}", path, warning));
+    println!(
+        "{} templates, {} errors, {} warnings",
+        templates.entities.len(),
+        issues.len(),
+        warnings.len()
+    );
+    // the game refuses to start with these, so fail the lint too
+    if !issues.is_empty() {
+        process::exit(1);
+    }
+}
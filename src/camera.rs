@@ -1,5 +1,6 @@
 use crate::prelude::*;
 
+#[derive(Clone, Copy, Serialize, Deserialize)]
 /// The game camera.
 pub struct Camera {
     /// Camera view left boundary.
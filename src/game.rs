@@ -0,0 +1,379 @@
+use crate::prelude::*;
+use std::error::Error;
+
+/// The dungeon level the Amulet of Yala is found on, starting at 0.
+pub const FINAL_LEVEL: u32 = 2;
+
+/// A game of dungeoncrawl, independent of any window: the `World`, its
+/// resources and the schedules that run the game logic.
+pub struct Game {
+    /// Storage for all entities and components through the `legion` world structure.
+    pub ecs: World,
+    /// Storage for Map and Camera resources.
+    pub resources: Resources,
+    /// Storage for the games systems.
+    input_systems: Schedule,
+    player_systems: Schedule,
+    monster_systems: Schedule,
+    /// Draws the game, kept apart from the logic schedules so they can run without a window.
+    render_systems: Schedule,
+    /// The seed every new game is started with, or `None` to pick a random one each time.
+    pub seed: Option<u64>,
+    /// The width of every level's map, in tiles.
+    pub map_width: i32,
+    /// The height of every level's map, in tiles.
+    pub map_height: i32,
+    /// The templates and vaults every level is built from.
+    pub data: GameData,
+    /// The vaults stamped onto the current level, for debugging.
+    pub placed_vaults: Vec<PlacedVault>,
+}
+
+impl Game {
+    /// Starts a new game with screen-sized maps.
+    /// * `data` - the templates and vaults, see `GameData::load`
+    /// * `seed` - the seed to start the game with, or `None` to pick a random one
+    pub fn new(data: GameData, seed: Option<u64>) -> Self {
+        Self::with_map_size(data, seed, SCREEN_WIDTH, SCREEN_HEIGHT)
+    }
+
+    /// Starts a new game whose maps are `map_width` by `map_height` tiles.
+    /// * `data` - the templates and vaults, see `GameData::load`
+    /// * `seed` - the seed to start the game with, or `None` to pick a random one
+    /// * `map_width` - the width of each map, at least `MIN_MAP_SIZE`
+    /// * `map_height` - the height of each map, at least `MIN_MAP_SIZE`
+    pub fn with_map_size(
+        data: GameData,
+        seed: Option<u64>,
+        map_width: i32,
+        map_height: i32,
+    ) -> Self {
+        let mut game = Self {
+            ecs: World::default(),
+            resources: Resources::default(),
+            input_systems: build_input_scheduler(),
+            player_systems: build_player_scheduler(),
+            monster_systems: build_monster_scheduler(),
+            render_systems: build_render_scheduler(),
+            seed,
+            map_width,
+            map_height,
+            data,
+            placed_vaults: Vec::new(),
+        };
+        game.reset();
+        game
+    }
+
+    /// Advances the game logic by one tick, without drawing anything.
+    /// * `key` - the key pressed during this tick, if any
+    pub fn step(&mut self, key: Option<VirtualKeyCode>) {
+        self.resources.insert(key);
+        let current_state = *self.resources.get::<TurnState>().unwrap();
+        match current_state {
+            TurnState::AwaitingInput => {
+                if let Some(key) = key {
+                    self.resources.get_mut::<Replay>().unwrap().record(key);
+                }
+                self.input_systems
+                    .execute(&mut self.ecs, &mut self.resources);
+            }
+            TurnState::PlayerTurn => {
+                self.player_systems
+                    .execute(&mut self.ecs, &mut self.resources);
+            }
+            TurnState::MonsterTurn => {
+                self.monster_systems
+                    .execute(&mut self.ecs, &mut self.resources);
+            }
+            TurnState::NextLevel => {
+                self.advance_level();
+            }
+            TurnState::GameOver | TurnState::Victory | TurnState::MainMenu => {}
+        }
+    }
+
+    /// The number of turns the player has taken in the current game.
+    pub fn turns(&self) -> usize {
+        self.resources.get::<Replay>().unwrap().inputs.len()
+    }
+
+    /// The phase the current turn is in.
+    pub fn turn_state(&self) -> TurnState {
+        *self.resources.get::<TurnState>().unwrap()
+    }
+
+    /// Queues the game's draw batches. Only useful when there is a window to
+    /// render them to, with `render_draw_buffer`.
+    pub fn render(&mut self) {
+        self.render_systems
+            .execute(&mut self.ecs, &mut self.resources);
+    }
+
+    /// Writes the game in progress to the save file.
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        save_game(&self.ecs, &self.resources)
+    }
+
+    /// Replaces the current game with the one in the save file.
+    pub fn continue_saved(&mut self) -> Result<(), Box<dyn Error>> {
+        let (ecs, resources) = load_game(&self.data.themes)?;
+        // the rest of the saved game is built at the size it was started with
+        {
+            let replay = resources
+                .get::<Replay>()
+                .ok_or("No replay in the saved game")?;
+            self.map_width = replay.map_width;
+            self.map_height = replay.map_height;
+        }
+        self.ecs = ecs;
+        self.resources = resources;
+        // saves don't record where the vaults were placed
+        self.placed_vaults.clear();
+        Ok(())
+    }
+
+    /// Throws the current game away and starts a new one on the first level.
+    pub fn reset(&mut self) {
+        self.ecs = World::default();
+        self.resources = Resources::default();
+        let game_rng = match self.seed {
+            Some(seed) => GameRng::new(seed),
+            None => GameRng::from_entropy(),
+        };
+        let mut map_builder = MapBuilder::new(
+            &mut game_rng.map_stream(0),
+            self.map_width,
+            self.map_height,
+            0,
+            &self.data,
+        );
+        self.placed_vaults = std::mem::take(&mut map_builder.placed_vaults);
+        spawn_player(&mut self.ecs, map_builder.player_start);
+        //spawn_amulet_of_yala(&mut self.ecs, map_builder.amulet_start);
+        let exit_idx = map_builder.map.point2d_to_index(map_builder.amulet_start);
+        map_builder.map.tiles[exit_idx] = TileType::Exit;
+        spawn_level(
+            &mut self.ecs,
+            &self.data.templates,
+            &mut game_rng.spawn_stream(0),
+            0,
+            &map_builder.monster_spawns,
+            &map_builder.regions,
+            &map_builder.entity_spawns,
+        );
+        self.resources.insert(map_builder.map);
+        self.resources.insert(Camera::new(map_builder.player_start));
+        self.resources.insert(TurnState::AwaitingInput);
+        self.resources.insert(map_builder.theme);
+        let mut log = GameLog::new();
+        log.add(
+            "You enter the dungeon in search of the Amulet of Yala.",
+            YELLOW,
+        );
+        self.resources.insert(log);
+        self.resources
+            .insert(Replay::new(game_rng.seed, self.map_width, self.map_height));
+        self.resources.insert(game_rng);
+    }
+
+    /// Moves the player and the items they carry down to a newly generated level.
+    pub fn advance_level(&mut self) {
+        let player_entity = *<Entity>::query()
+            .filter(component::<Player>())
+            .iter(&self.ecs)
+            .next()
+            .unwrap();
+        use std::collections::HashSet;
+        let mut entities_to_keep = HashSet::new();
+        entities_to_keep.insert(player_entity);
+        <(Entity, &Carried)>::query()
+            .iter(&self.ecs)
+            .filter(|(_e, carry)| carry.0 == player_entity)
+            .map(|(e, _carry)| *e)
+            .for_each(|e| {
+                entities_to_keep.insert(e);
+            });
+        let mut cb = CommandBuffer::new(&self.ecs);
+        for e in Entity::query().iter(&self.ecs) {
+            if !entities_to_keep.contains(e) {
+                cb.remove(*e);
+            }
+        }
+        cb.flush(&mut self.ecs);
+        <&mut FieldOfView>::query()
+            .iter_mut(&mut self.ecs)
+            .for_each(|fov| fov.is_dirty = true);
+        let map_level = <&Player>::query().iter(&self.ecs).next().unwrap().map_level + 1;
+        let (mut map_rng, mut spawn_rng) = {
+            let game_rng = self.resources.get::<GameRng>().unwrap();
+            (
+                game_rng.map_stream(map_level),
+                game_rng.spawn_stream(map_level),
+            )
+        };
+        let mut map_builder = MapBuilder::new(
+            &mut map_rng,
+            self.map_width,
+            self.map_height,
+            map_level,
+            &self.data,
+        );
+        self.placed_vaults = std::mem::take(&mut map_builder.placed_vaults);
+        <(&mut Player, &mut Point)>::query()
+            .iter_mut(&mut self.ecs)
+            .for_each(|(player, pos)| {
+                player.map_level = map_level;
+                pos.x = map_builder.player_start.x;
+                pos.y = map_builder.player_start.y;
+            });
+        if map_level == FINAL_LEVEL {
+            spawn_amulet_of_yala(&mut self.ecs, map_builder.amulet_start);
+        } else {
+            let exit_idx = map_builder.map.point2d_to_index(map_builder.amulet_start);
+            map_builder.map.tiles[exit_idx] = TileType::Exit;
+        }
+        spawn_level(
+            &mut self.ecs,
+            &self.data.templates,
+            &mut spawn_rng,
+            map_level as usize,
+            &map_builder.monster_spawns,
+            &map_builder.regions,
+            &map_builder.entity_spawns,
+        );
+        self.resources.insert(map_builder.map);
+        self.resources.insert(Camera::new(map_builder.player_start));
+        self.resources.insert(TurnState::AwaitingInput);
+        self.resources.insert(map_builder.theme);
+        self.resources.get_mut::<GameLog>().unwrap().add(
+            format!("You descend to dungeon level {}.", map_level + 1),
+            YELLOW,
+        );
+    }
+
+    /// Replaces the templates used to populate future levels, logging the
+    /// result on the HUD. Used to reload the templates while developing.
+    /// * `reloaded` - the reloaded templates, or why they could not be loaded
+    /// * `respawn` - true to also repopulate the current level with them
+    pub fn reload_templates(&mut self, reloaded: Result<Templates, TemplateError>, respawn: bool) {
+        match reloaded {
+            Ok(templates) => {
+                self.data.templates = templates;
+                // vaults and layouts were checked against the old templates, and
+                // a spawn naming a template that is gone would be skipped
+                let missing: Vec<String> = self
+                    .data
+                    .vaults
+                    .missing_templates(&self.data.templates)
+                    .into_iter()
+                    .chain(self.data.layouts.missing_templates(&self.data.templates))
+                    .collect();
+                {
+                    let mut log = self.resources.get_mut::<GameLog>().unwrap();
+                    log.add("Templates reloaded", CYAN);
+                    missing.iter().for_each(|problem| log.add(problem, RED));
+                }
+                if respawn {
+                    self.respawn_level();
+                }
+            }
+            Err(e) => {
+                // keep playing with the old templates until the file is fixed
+                let mut log = self.resources.get_mut::<GameLog>().unwrap();
+                e.to_string().lines().for_each(|line| log.add(line, RED));
+            }
+        }
+    }
+
+    /// Replaces the monsters and items lying on the current level with fresh
+    /// ones spawned from the current templates. Carried items are kept.
+    pub fn respawn_level(&mut self) {
+        let mut cb = CommandBuffer::new(&self.ecs);
+        <Entity>::query()
+            .filter(
+                (component::<Enemy>() | component::<Item>())
+                    & component::<Point>()
+                    & !component::<AmuletOfYala>(),
+            )
+            .iter(&self.ecs)
+            .for_each(|entity| cb.remove(*entity));
+        cb.flush(&mut self.ecs);
+
+        // regenerating the level from its streams yields the same map and spawn points
+        let map_level = <&Player>::query().iter(&self.ecs).next().unwrap().map_level;
+        let (mut map_rng, mut spawn_rng) = {
+            let game_rng = self.resources.get::<GameRng>().unwrap();
+            (
+                game_rng.map_stream(map_level),
+                game_rng.spawn_stream(map_level),
+            )
+        };
+        let map_builder = MapBuilder::new(
+            &mut map_rng,
+            self.map_width,
+            self.map_height,
+            map_level,
+            &self.data,
+        );
+        let player_pos = *<&Point>::query()
+            .filter(component::<Player>())
+            .iter(&self.ecs)
+            .next()
+            .unwrap();
+        let spawn_points: Vec<Point> = map_builder
+            .monster_spawns
+            .into_iter()
+            .filter(|pt| *pt != player_pos)
+            .collect();
+        spawn_level(
+            &mut self.ecs,
+            &self.data.templates,
+            &mut spawn_rng,
+            map_level as usize,
+            &spawn_points,
+            &map_builder.regions,
+            &map_builder.entity_spawns,
+        );
+        self.resources
+            .get_mut::<GameLog>()
+            .unwrap()
+            .add("The level has been repopulated", CYAN);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every tile of the level, and the name and position of everything on it.
+    fn level(game: &Game) -> (Vec<TileType>, Vec<(i32, i32, String)>) {
+        let tiles = game.resources.get::<Map>().unwrap().tiles.clone();
+        let mut spawns: Vec<(i32, i32, String)> = <(&Point, &Name)>::query()
+            .iter(&game.ecs)
+            .map(|(pos, name)| (pos.x, pos.y, name.0.clone()))
+            .collect();
+        spawns.sort();
+        (tiles, spawns)
+    }
+
+    #[test]
+    fn same_seed_builds_the_same_levels() {
+        let data = GameData::load().unwrap();
+        let mut first = Game::new(data.clone(), Some(1234));
+        let mut second = Game::new(data, Some(1234));
+        assert_eq!(level(&first), level(&second));
+
+        first.advance_level();
+        second.advance_level();
+        assert_eq!(level(&first), level(&second));
+    }
+
+    #[test]
+    fn different_seeds_build_different_levels() {
+        let data = GameData::load().unwrap();
+        let first = Game::new(data.clone(), Some(1234));
+        let second = Game::new(data, Some(4321));
+        assert_ne!(level(&first), level(&second));
+    }
+}
@@ -0,0 +1,101 @@
+use crate::prelude::*;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+/// Everything designers can change without touching Rust, loaded and
+/// validated from `resources/` before the game starts.
+#[derive(Clone, Debug)]
+pub struct GameData {
+    /// The monsters and items levels are populated from, see `TEMPLATE_FILE`.
+    pub templates: Templates,
+    /// The hand-made vaults stamped onto levels, see `VAULT_DIR`.
+    pub vaults: Vaults,
+    /// The map themes defined in data files, see `THEME_DIR`.
+    pub themes: Themes,
+    /// The hand-made layouts whole levels can be, see `LAYOUT_DIR`.
+    pub layouts: Vaults,
+    /// How each dungeon level is built, see `PLAN_FILE`.
+    pub plan: DungeonPlan,
+}
+
+impl GameData {
+    /// Loads every data file from its usual place.
+    pub fn load() -> Result<Self, Box<dyn Error>> {
+        let templates = Templates::load(TEMPLATE_FILE)?;
+        let vaults = Vaults::load(VAULT_DIR, &templates)?;
+        let themes = Themes::load(THEME_DIR)?;
+        let layouts = Vaults::load(LAYOUT_DIR, &templates)?;
+        let plan = DungeonPlan::load(PLAN_FILE, &vaults, &layouts, &themes)?;
+        Ok(Self {
+            templates,
+            vaults,
+            themes,
+            layouts,
+            plan,
+        })
+    }
+}
+
+/// Why one of a directory of data files, such as vaults or themes, could not be loaded.
+#[derive(Debug)]
+pub struct DataFileError {
+    /// The data file, or the directory if it could not be read.
+    pub path: String,
+    /// The line of the error, starting at 1, or 0 if it is not known.
+    pub line: usize,
+    pub message: String,
+}
+
+impl DataFileError {
+    /// Describes a problem with a whole file.
+    /// * `path` - the data file
+    /// * `message` - what is wrong with it
+    pub fn new<S: ToString>(path: &str, message: S) -> Self {
+        Self {
+            path: path.to_string(),
+            line: 0,
+            message: message.to_string(),
+        }
+    }
+
+    /// Describes a file that is not valid RON, or doesn't match what was expected.
+    /// * `path` - the data file
+    /// * `error` - the error from `ron`
+    pub fn from_ron(path: &str, error: ron::Error) -> Self {
+        Self {
+            path: path.to_string(),
+            line: error.position.line,
+            message: error.code.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for DataFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.line == 0 {
+            write!(f, "{}: {}", self.path, self.message)
+        } else {
+            write!(f, "{}:{}: {}", self.path, self.line, self.message)
+        }
+    }
+}
+
+impl Error for DataFileError {}
+
+/// Lists the `.ron` files in a directory, in file name order so that seeds
+/// produce the same levels on every machine.
+/// * `dir` - the directory to list
+pub fn ron_files(dir: &str) -> Result<Vec<PathBuf>, DataFileError> {
+    let mut paths = fs::read_dir(dir)
+        .and_then(|entries| {
+            entries
+                .map(|entry| entry.map(|e| e.path()))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|e| DataFileError::new(dir, e))?;
+    paths.retain(|path| path.extension().is_some_and(|ext| ext == "ron"));
+    paths.sort();
+    Ok(paths)
+}
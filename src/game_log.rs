@@ -0,0 +1,121 @@
+use crate::prelude::*;
+
+/// The most entries kept in the log; older ones are forgotten.
+const MAX_ENTRIES: usize = 500;
+/// The number of recent entries shown on the HUD.
+pub const HUD_LOG_LINES: usize = 5;
+
+/// A single line of the game log.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LogEntry {
+    /// What happened, e.g. "The Orc hits you for 2".
+    pub text: String,
+    /// The color the entry is drawn in.
+    pub color: RGB,
+}
+
+/// Everything that has happened to the player, written to by the systems and
+/// drawn on the HUD, so players can see how their hero came to an end.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct GameLog {
+    entries: Vec<LogEntry>,
+}
+
+impl GameLog {
+    /// Creates an empty log.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an entry to the end of the log.
+    /// * `text` - what happened
+    /// * `color` - the color to draw the entry in, e.g. `RED`
+    pub fn add<S: ToString, C: Into<RGB>>(&mut self, text: S, color: C) {
+        self.entries.push(LogEntry {
+            text: text.to_string(),
+            color: color.into(),
+        });
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.remove(0);
+        }
+    }
+
+    /// Every entry still remembered, oldest first.
+    pub fn entries(&self) -> &[LogEntry] {
+        &self.entries
+    }
+
+    /// The last `count` entries, oldest first.
+    /// * `count` - the most entries to return
+    pub fn recent(&self, count: usize) -> &[LogEntry] {
+        &self.entries[self.entries.len().saturating_sub(count)..]
+    }
+}
+
+/// The full-screen view of the game log, scrolled with the cursor keys.
+pub struct LogHistory {
+    /// The number of entries scrolled back from the newest.
+    scroll: usize,
+}
+
+impl LogHistory {
+    /// Opens the history at the newest entries.
+    pub fn new() -> Self {
+        Self { scroll: 0 }
+    }
+
+    /// Scrolls the history with the cursor and page keys, and returns false
+    /// once the player closes it with [L].
+    /// * `ctx` - the running `BTerm`, for the key pressed
+    /// * `log` - the log being viewed
+    pub fn update(&mut self, ctx: &BTerm, log: &GameLog) -> bool {
+        let page = Self::page_size();
+        let last = log.entries().len().saturating_sub(page);
+        match ctx.key {
+            Some(VirtualKeyCode::L) => return false,
+            Some(VirtualKeyCode::Up) => self.scroll += 1,
+            Some(VirtualKeyCode::Down) => self.scroll = self.scroll.saturating_sub(1),
+            Some(VirtualKeyCode::PageUp) => self.scroll += page,
+            Some(VirtualKeyCode::PageDown) => self.scroll = self.scroll.saturating_sub(page),
+            Some(VirtualKeyCode::Home) => self.scroll = last,
+            Some(VirtualKeyCode::End) => self.scroll = 0,
+            _ => {}
+        }
+        self.scroll = usize::min(self.scroll, last);
+        true
+    }
+
+    /// Draws a page of the log over the whole screen.
+    /// * `ctx` - the running `BTerm`
+    /// * `log` - the log being viewed
+    pub fn render(&self, ctx: &mut BTerm, log: &GameLog) {
+        ctx.set_active_console(2);
+        ctx.print_color_centered(1, YELLOW, BLACK, "Message Log");
+        let entries = log.entries();
+        let end = entries.len() - usize::min(self.scroll, entries.len());
+        let start = end.saturating_sub(Self::page_size());
+        entries[start..end]
+            .iter()
+            .enumerate()
+            .for_each(|(y, entry)| {
+                ctx.print_color(2, y as i32 + 3, entry.color, BLACK, &entry.text);
+            });
+        ctx.print_color_centered(
+            SCREEN_HEIGHT * 2 - 1,
+            WHITE,
+            BLACK,
+            "[Up/Down/PgUp/PgDn/Home/End] scroll  [L] back to the game",
+        );
+    }
+
+    // The number of entries that fit between the title and the key help.
+    fn page_size() -> usize {
+        (SCREEN_HEIGHT * 2 - 5) as usize
+    }
+}
+
+impl Default for LogHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,70 @@
+use crate::prelude::*;
+
+/// Salt mixed into the seed for the map generation stream.
+const MAP_STREAM: u64 = 0x6d61_705f_6765_6e00;
+/// Salt mixed into the seed for the entity spawning stream.
+const SPAWN_STREAM: u64 = 0x7370_6177_6e00_0000;
+/// Salt mixed into the seed for the monster AI stream.
+const AI_STREAM: u64 = 0x6169_0000_0000_0000;
+
+#[derive(Clone, Serialize, Deserialize)]
+/// The random number streams for a single run of the game.
+///
+/// Every stream is derived from one seed, so the same seed always yields the
+/// same maps, the same spawns and the same monster behaviour.
+pub struct GameRng {
+    /// The seed the run was started with.
+    pub seed: u64,
+    /// The stream used by monster AI, advanced every time a monster makes a choice.
+    pub ai: RandomNumberGenerator,
+}
+
+impl GameRng {
+    /// Creates the streams for a run started with `seed`.
+    /// * `seed` - the seed supplied on the command line, or a random one
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            ai: RandomNumberGenerator::seeded(seed ^ AI_STREAM),
+        }
+    }
+
+    /// Creates the streams for a run with a randomly chosen seed.
+    pub fn from_entropy() -> Self {
+        Self::new(RandomNumberGenerator::new().next_u64())
+    }
+
+    /// Creates the map generation stream for a dungeon level.
+    /// * `level` - the dungeon level being generated, starting at 0
+    pub fn map_stream(&self, level: u32) -> RandomNumberGenerator {
+        Self::derive(self.seed, MAP_STREAM, level)
+    }
+
+    /// Creates the entity spawning stream for a dungeon level.
+    /// * `level` - the dungeon level being populated, starting at 0
+    pub fn spawn_stream(&self, level: u32) -> RandomNumberGenerator {
+        Self::derive(self.seed, SPAWN_STREAM, level)
+    }
+
+    // Derives an independent stream from the seed, a stream salt and a level,
+    // so that generating one level never shifts the numbers used by another.
+    fn derive(seed: u64, salt: u64, level: u32) -> RandomNumberGenerator {
+        let level_mix = (level as u64 + 1).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        RandomNumberGenerator::seeded(seed ^ salt ^ level_mix)
+    }
+}
+
+/// Turns a seed typed by a player into a numeric seed.
+///
+/// Whole numbers are used as they are; any other text, such as a date for a
+/// daily challenge, is hashed with FNV-1a so it is stable between builds.
+/// * `text` - the seed as supplied on the command line or in the environment
+pub fn parse_seed(text: &str) -> u64 {
+    text.trim().parse().unwrap_or_else(|_| {
+        text.trim()
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+            })
+    })
+}
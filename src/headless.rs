@@ -0,0 +1,225 @@
+use crate::prelude::*;
+use std::error::Error;
+use std::fs;
+
+/// The keys the game responds to, and the names used for them in scripts.
+const KEY_NAMES: [(&str, VirtualKeyCode); 17] = [
+    ("Left", VirtualKeyCode::Left),
+    ("Right", VirtualKeyCode::Right),
+    ("Up", VirtualKeyCode::Up),
+    ("Down", VirtualKeyCode::Down),
+    ("G", VirtualKeyCode::G),
+    ("O", VirtualKeyCode::O),
+    ("C", VirtualKeyCode::C),
+    ("Space", VirtualKeyCode::Space),
+    ("1", VirtualKeyCode::Key1),
+    ("2", VirtualKeyCode::Key2),
+    ("3", VirtualKeyCode::Key3),
+    ("4", VirtualKeyCode::Key4),
+    ("5", VirtualKeyCode::Key5),
+    ("6", VirtualKeyCode::Key6),
+    ("7", VirtualKeyCode::Key7),
+    ("8", VirtualKeyCode::Key8),
+    ("9", VirtualKeyCode::Key9),
+];
+
+/// Looks up a key by its script name, e.g. `Up` or `1`.
+/// * `name` - the name of the key, matched case-insensitively
+pub fn key_from_name(name: &str) -> Option<VirtualKeyCode> {
+    KEY_NAMES
+        .iter()
+        .find(|(key_name, _)| key_name.eq_ignore_ascii_case(name))
+        .map(|(_, key)| *key)
+}
+
+/// Returns the script name of a key, or `None` if the game has no use for it.
+/// * `key` - the key to name
+pub fn key_name(key: VirtualKeyCode) -> Option<&'static str> {
+    KEY_NAMES
+        .iter()
+        .find(|(_, named_key)| *named_key == key)
+        .map(|(name, _)| *name)
+}
+
+/// Chooses the keys pressed in a game that has no window.
+pub trait InputSource {
+    /// Returns the key to press while the game is awaiting input, or `None` to
+    /// stop playing.
+    /// * `ecs` - the game's `World`, so that bots can look around
+    /// * `resources` - the game's resources, including the `Map`
+    fn next_key(&mut self, ecs: &World, resources: &Resources) -> Option<VirtualKeyCode>;
+}
+
+/// Presses the keys listed in a script, one per turn, then stops.
+pub struct ScriptedInput {
+    keys: Vec<VirtualKeyCode>,
+    next: usize,
+}
+
+impl ScriptedInput {
+    /// Reads a script of whitespace separated key names, such as `Up Up G 1`.
+    /// * `path` - the script file
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        let keys = fs::read_to_string(path)?
+            .split_whitespace()
+            .map(|name| key_from_name(name).ok_or(format!("Unknown key {} in {}", name, path)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { keys, next: 0 })
+    }
+
+    /// Presses the keys recorded in a replay.
+    /// * `replay` - the recorded game
+    pub fn from_replay(replay: &Replay) -> Self {
+        let keys = replay
+            .inputs
+            .iter()
+            .filter_map(|input| key_from_name(&input.key))
+            .collect();
+        Self { keys, next: 0 }
+    }
+}
+
+impl InputSource for ScriptedInput {
+    fn next_key(&mut self, _ecs: &World, _resources: &Resources) -> Option<VirtualKeyCode> {
+        let key = self.keys.get(self.next).copied();
+        self.next += 1;
+        key
+    }
+}
+
+/// Mashes keys at random, which is enough to shake out crashes.
+pub struct RandomInput {
+    rng: RandomNumberGenerator,
+}
+
+impl RandomInput {
+    /// Creates a random player whose choices are reproducible from `seed`.
+    /// * `seed` - the seed of the game being played
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: RandomNumberGenerator::seeded(seed),
+        }
+    }
+}
+
+impl InputSource for RandomInput {
+    fn next_key(&mut self, _ecs: &World, _resources: &Resources) -> Option<VirtualKeyCode> {
+        // mostly walk, sometimes pick things up or use the first item
+        let key = match self.rng.range(0, 12) {
+            0..=1 => VirtualKeyCode::Left,
+            2..=3 => VirtualKeyCode::Right,
+            4..=5 => VirtualKeyCode::Up,
+            6..=7 => VirtualKeyCode::Down,
+            8 => VirtualKeyCode::G,
+            9 => VirtualKeyCode::Key1,
+            _ => VirtualKeyCode::Space,
+        };
+        Some(key)
+    }
+}
+
+/// The settings for a batch of headless games.
+pub struct HeadlessOptions {
+    /// The seed of the first game; each following game adds one to it.
+    pub seed: Option<u64>,
+    /// The number of games to play.
+    pub games: u64,
+    /// The number of turns after which a game is abandoned.
+    pub max_turns: usize,
+    /// The width and height of every map.
+    pub map_size: (i32, i32),
+    /// A script of keys to press, otherwise keys are pressed at random.
+    pub script: Option<String>,
+    /// A replay to play back instead, with the seed it was recorded with.
+    pub replay: Option<String>,
+}
+
+/// How a headless game came to an end.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Outcome {
+    Won,
+    Died,
+    /// The input ran out, or the game reached the turn limit.
+    Stopped,
+}
+
+/// Plays a batch of games without a window, printing how each one ended.
+/// * `options` - the number of games to play and how to play them
+pub fn run_headless(options: &HeadlessOptions) -> Result<(), Box<dyn Error>> {
+    let replay = match &options.replay {
+        Some(path) => Some(Replay::load(path)?),
+        None => None,
+    };
+    let first_seed = replay
+        .as_ref()
+        .map(|replay| replay.seed)
+        .or(options.seed)
+        .unwrap_or_else(|| RandomNumberGenerator::new().next_u64());
+    let (map_width, map_height) = replay
+        .as_ref()
+        .map(|replay| (replay.map_width, replay.map_height))
+        .unwrap_or(options.map_size);
+    let data = GameData::load()?;
+    let mut outcomes = Vec::new();
+    for number in 0..options.games {
+        let seed = first_seed.wrapping_add(number);
+        let mut input: Box<dyn InputSource> = match (&replay, &options.script) {
+            (Some(replay), _) => Box::new(ScriptedInput::from_replay(replay)),
+            (None, Some(path)) => Box::new(ScriptedInput::load(path)?),
+            (None, None) => Box::new(RandomInput::new(seed)),
+        };
+        let mut game = Game::with_map_size(data.clone(), Some(seed), map_width, map_height);
+        let (outcome, turns) = play_game(&mut game, input.as_mut(), options.max_turns);
+        let map_level = <&Player>::query().iter(&game.ecs).next().unwrap().map_level;
+        println!(
+            "Game {} (seed {}): {:?} on dungeon level {} after {} turns",
+            number + 1,
+            seed,
+            outcome,
+            map_level + 1,
+            turns
+        );
+        if outcome == Outcome::Died {
+            let log = game.resources.get::<GameLog>().unwrap();
+            log.recent(3)
+                .iter()
+                .for_each(|entry| println!("    {}", entry.text));
+        }
+        outcomes.push(outcome);
+    }
+    let count = |outcome| outcomes.iter().filter(|o| **o == outcome).count();
+    println!(
+        "Played {} games: {} won, {} died, {} stopped",
+        outcomes.len(),
+        count(Outcome::Won),
+        count(Outcome::Died),
+        count(Outcome::Stopped)
+    );
+    Ok(())
+}
+
+// Steps the game until it ends, asking the input source for a key every time
+// the game awaits input. Returns the outcome and the number of turns taken.
+fn play_game(game: &mut Game, input: &mut dyn InputSource, max_turns: usize) -> (Outcome, usize) {
+    let mut turns = 0;
+    loop {
+        let current_state = game.turn_state();
+        match current_state {
+            TurnState::Victory => return (Outcome::Won, turns),
+            TurnState::GameOver => return (Outcome::Died, turns),
+            TurnState::AwaitingInput => {
+                if turns >= max_turns {
+                    return (Outcome::Stopped, turns);
+                }
+                match input.next_key(&game.ecs, &game.resources) {
+                    Some(key) => {
+                        game.step(Some(key));
+                        turns += 1;
+                    }
+                    None => return (Outcome::Stopped, turns),
+                }
+            }
+            _ => game.step(None),
+        }
+    }
+}
@@ -0,0 +1,55 @@
+//! The dungeoncrawl game as a library, so that tools such as map previewers and
+//! balance simulations can link against the game rather than copy it.
+
+// add the modules to your project with mod
+pub mod camera;
+pub mod components;
+pub mod game;
+pub mod game_data;
+pub mod game_log;
+pub mod game_rng;
+pub mod headless;
+pub mod map;
+pub mod map_builder;
+pub mod mapgen_viewer;
+pub mod replay;
+pub mod save_game;
+pub mod spawner;
+pub mod systems;
+pub mod turn_state;
+
+// declare a new module in source code
+pub mod prelude {
+    // publicly re-export the crates bracket_lib::prelude
+    // and Legion making them available within prelude
+    pub use bracket_lib::prelude::*;
+    pub use legion::systems::CommandBuffer;
+    pub use legion::world::SubWorld;
+    pub use legion::*;
+    pub use serde::{Deserialize, Serialize};
+    // public constants available within prelude
+    /// The screen width.
+    pub const SCREEN_WIDTH: i32 = 80;
+    /// The screen height.
+    pub const SCREEN_HEIGHT: i32 = 50;
+    /// The display width.
+    pub const DISPLAY_WIDTH: i32 = SCREEN_WIDTH / 2;
+    /// The display height.
+    pub const DISPLAY_HEIGHT: i32 = SCREEN_HEIGHT / 2;
+    // re-export the crates as public modules available within prelude
+    pub use crate::camera::*;
+    pub use crate::components::*;
+    pub use crate::game::*;
+    pub use crate::game_data::*;
+    pub use crate::game_log::*;
+    pub use crate::game_rng::*;
+    pub use crate::headless::*;
+    pub use crate::map::*;
+    pub use crate::map_builder::*;
+    pub use crate::mapgen_viewer::*;
+    pub use crate::replay::*;
+    pub use crate::save_game::*;
+    pub use crate::spawner::*;
+    pub use crate::systems::*;
+    pub use crate::turn_state::*;
+}
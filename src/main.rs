@@ -1,78 +1,128 @@
+use dungeoncrawl::prelude::*;
 use std::env;
 
-// add the modules to your project with mod
-mod camera;
-mod components;
-mod map;
-mod map_builder;
-mod spawner;
-mod systems;
-mod turn_state;
-
-// declare a new module in source code
-mod prelude {
-    // publicly re-export the crates bracket_lib::prelude
-    // and Legion making them available within prelude
-    pub use bracket_lib::prelude::*;
-    pub use legion::systems::CommandBuffer;
-    pub use legion::world::SubWorld;
-    pub use legion::*;
-    // public constants available within prelude
-    /// The screen width.
-    pub const SCREEN_WIDTH: i32 = 80;
-    /// The screen height.
-    pub const SCREEN_HEIGHT: i32 = 50;
-    /// The display width.
-    pub const DISPLAY_WIDTH: i32 = SCREEN_WIDTH / 2;
-    /// The display height.
-    pub const DISPLAY_HEIGHT: i32 = SCREEN_HEIGHT / 2;
-    // re-export the crates as public modules available within prelude
-    pub use crate::camera::*;
-    pub use crate::components::*;
-    pub use crate::map::*;
-    pub use crate::map_builder::*;
-    pub use crate::spawner::*;
-    pub use crate::systems::*;
-    pub use crate::turn_state::*;
-}
-
-// make prelude available to the main scope
-use prelude::*;
-
-/// A snapshot of the current game.
+/// The game as played in a window, with its menus, saving and replays.
 struct State {
-    /// Storage for all entities and components through the `legion` world structure.
-    ecs: World,
-    /// Storage for Map and Camera resources.
-    resources: Resources,
-    /// Storage for the games systems.
-    input_systems: Schedule,
-    player_systems: Schedule,
-    monster_systems: Schedule,
+    /// The game being played.
+    game: Game,
+    /// Feeds recorded keys to the game instead of the keyboard, when watching a replay.
+    replay: Option<ReplayPlayer>,
+    /// The full-screen message log, while it is open. The game waits meanwhile.
+    log_history: Option<LogHistory>,
+    /// Reloads the templates whenever they are edited, in dev mode.
+    template_watcher: Option<TemplateWatcher>,
+    /// True to repopulate the current level whenever the templates are reloaded.
+    respawn_on_reload: bool,
+    /// True to print the vaults placed on every level to the terminal.
+    debug_vaults: bool,
 }
 
 impl State {
     /// Initialises the game `State`.
-    fn new() -> Self {
-        let mut ecs = World::default();
-        let mut resources = Resources::default();
-        let mut rng = RandomNumberGenerator::new();
-        let mut map_builder = MapBuilder::new(&mut rng);
-        spawn_player(&mut ecs, map_builder.player_start);
-        //spawn_amulet_of_yala(&mut ecs, map_builder.amulet_start);
-        let exit_idx = map_builder.map.point2d_to_index(map_builder.amulet_start);
-        map_builder.map.tiles[exit_idx] = TileType::Exit;
-        spawn_level(&mut ecs, &mut rng, 0, &map_builder.monster_spawns);
-        resources.insert(map_builder.map);
-        resources.insert(Camera::new(map_builder.player_start));
-        resources.insert(TurnState::AwaitingInput);
-        resources.insert(map_builder.theme);
-        Self {
-            ecs,
-            resources,
-            input_systems: build_input_scheduler(),
-            player_systems: build_player_scheduler(),
-            monster_systems: build_monster_scheduler(),
+    /// * `data` - the validated templates and vaults
+    /// * `seed` - the seed requested by the player, or `None` to pick a random one
+    /// * `map_size` - the width and height of every map
+    fn new(data: GameData, seed: Option<u64>, map_size: (i32, i32)) -> Self {
+        let state = Self {
+            game: Game::with_map_size(data, seed, map_size.0, map_size.1),
+            replay: None,
+            log_history: None,
+            template_watcher: None,
+            respawn_on_reload: false,
+            debug_vaults: false,
+        };
+        state.report_seed();
+        state
+    }
+
+    /// Prints the seed of the game that was just started, so it can be played again.
+    fn report_seed(&self) {
+        let seed = self.game.resources.get::<GameRng>().unwrap().seed;
+        println!("Starting a new game with seed {}", seed);
+    }
+
+    /// Prints the vaults placed on the current level, with `--debug-vaults`.
+    fn report_vaults(&self) {
+        if !self.debug_vaults {
+            return;
+        }
+        let map_level = <&Player>::query()
+            .iter(&self.game.ecs)
+            .next()
+            .unwrap()
+            .map_level;
+        self.game.placed_vaults.iter().for_each(|placed| {
+            println!(
+                "Dungeon level {}: placed vault {} at ({}, {}), {}",
+                map_level + 1,
+                placed.name,
+                placed.area.x1,
+                placed.area.y1,
+                placed.orientation
+            )
+        });
+    }
+
+    /// Throws the finished game away and starts a new one.
+    fn play_again(&mut self) {
+        self.game.reset();
+        self.report_seed();
+        self.report_vaults();
+    }
+
+    fn main_menu(&mut self, ctx: &mut BTerm) {
+        ctx.set_active_console(2);
+        ctx.print_color_centered(2, YELLOW, BLACK, "Welcome back to the dungeon.");
+        ctx.print_color_centered(
+            4,
+            WHITE,
+            BLACK,
+            "Your hero's journey was interrupted, but the Amulet of Yala still awaits.",
+        );
+        ctx.print_color_centered(7, GREEN, BLACK, "Press C to continue.");
+        ctx.print_color_centered(8, GREEN, BLACK, "Press N to start a new game.");
+
+        match ctx.key {
+            Some(VirtualKeyCode::C) => self.continue_game(),
+            Some(VirtualKeyCode::N) => {
+                discard_save();
+                self.game.resources.insert(TurnState::AwaitingInput);
+            }
+            _ => {}
+        }
+    }
+
+    /// Replaces the new game prepared by `State::new` with the saved game.
+    fn continue_game(&mut self) {
+        if let Err(e) = self.game.continue_saved() {
+            println!("Unable to continue the saved game: {}", e);
+            self.game.resources.insert(TurnState::AwaitingInput);
+        }
+    }
+
+    /// Saves the game in progress and closes the window. Finished games are
+    /// never saved, so a dead hero can't be continued.
+    fn save_and_quit(&mut self, ctx: &mut BTerm) {
+        match self.game.turn_state() {
+            TurnState::GameOver | TurnState::Victory => discard_save(),
+            TurnState::MainMenu => {}
+            _ => {
+                if let Err(e) = self.game.save() {
+                    println!("Unable to save the game: {}", e);
+                }
+            }
+        }
+        ctx.quit();
+    }
+
+    /// Shows the full-screen message log until the player closes it.
+    fn message_log(&mut self, ctx: &mut BTerm) {
+        let log = self.game.resources.get::<GameLog>().unwrap();
+        let history = self.log_history.as_mut().unwrap();
+        if history.update(ctx, &log) {
+            history.render(ctx, &log);
+        } else {
+            self.log_history = None;
         }
     }
 
@@ -100,7 +150,7 @@ impl State {
         ctx.print_color_centered(9, GREEN, BLACK, "Press 1 to play again.");
 
         if let Some(VirtualKeyCode::Key1) = ctx.key {
-            self.reset_game_state();
+            self.play_again();
         }
     }
 
@@ -121,80 +171,9 @@ impl State {
         );
         ctx.print_color_centered(7, GREEN, BLACK, "Press 1 to play again.");
         if let Some(VirtualKeyCode::Key1) = ctx.key {
-            self.reset_game_state();
+            self.play_again();
         }
     }
-
-    fn reset_game_state(&mut self) {
-        self.ecs = World::default();
-        self.resources = Resources::default();
-        let mut rng = RandomNumberGenerator::new();
-        let mut map_builder = MapBuilder::new(&mut rng);
-        spawn_player(&mut self.ecs, map_builder.player_start);
-        //spawn_amulet_of_yala(&mut self.ecs, map_builder.amulet_start);
-        let exit_idx = map_builder.map.point2d_to_index(map_builder.amulet_start);
-        map_builder.map.tiles[exit_idx] = TileType::Exit;
-        spawn_level(&mut self.ecs, &mut rng, 0, &map_builder.monster_spawns);
-        self.resources.insert(map_builder.map);
-        self.resources.insert(Camera::new(map_builder.player_start));
-        self.resources.insert(TurnState::AwaitingInput);
-        self.resources.insert(map_builder.theme);
-    }
-
-    fn advance_level(&mut self) {
-        let player_entity = *<Entity>::query()
-            .filter(component::<Player>())
-            .iter(&mut self.ecs)
-            .nth(0)
-            .unwrap();
-        use std::collections::HashSet;
-        let mut entities_to_keep = HashSet::new();
-        entities_to_keep.insert(player_entity);
-        <(Entity, &Carried)>::query()
-            .iter(&self.ecs)
-            .filter(|(_e, carry)| carry.0 == player_entity)
-            .map(|(e, _carry)| *e)
-            .for_each(|e| {
-                entities_to_keep.insert(e);
-            });
-        let mut cb = CommandBuffer::new(&mut self.ecs);
-        for e in Entity::query().iter(&self.ecs) {
-            if !entities_to_keep.contains(e) {
-                cb.remove(*e);
-            }
-        }
-        cb.flush(&mut self.ecs);
-        <&mut FieldOfView>::query()
-            .iter_mut(&mut self.ecs)
-            .for_each(|fov| fov.is_dirty = true);
-        let mut rng = RandomNumberGenerator::new();
-        let mut map_builder = MapBuilder::new(&mut rng);
-        let mut map_level = 0;
-        <(&mut Player, &mut Point)>::query()
-            .iter_mut(&mut self.ecs)
-            .for_each(|(player, pos)| {
-                player.map_level += 1;
-                map_level = player.map_level;
-                pos.x = map_builder.player_start.x;
-                pos.y = map_builder.player_start.y;
-            });
-        if map_level == 2 {
-            spawn_amulet_of_yala(&mut self.ecs, map_builder.amulet_start);
-        } else {
-            let exit_idx = map_builder.map.point2d_to_index(map_builder.amulet_start);
-            map_builder.map.tiles[exit_idx] = TileType::Exit;
-        }
-        spawn_level(
-            &mut self.ecs,
-            &mut rng,
-            map_level as usize,
-            &map_builder.monster_spawns,
-        );
-        self.resources.insert(map_builder.map);
-        self.resources.insert(Camera::new(map_builder.player_start));
-        self.resources.insert(TurnState::AwaitingInput);
-        self.resources.insert(map_builder.theme);
-    }
 }
 
 // implements the `GameState` trait for the `State` struct
@@ -210,41 +189,171 @@ impl GameState for State {
         ctx.cls();
         ctx.set_active_console(2);
         ctx.cls();
-        self.resources.insert(ctx.key);
-        ctx.set_active_console(0);
-        self.resources.insert(Point::from_tuple(ctx.mouse_pos()));
-        let current_state = self.resources.get::<TurnState>().unwrap().clone();
-        match current_state {
-            TurnState::AwaitingInput => self
-                .input_systems
-                .execute(&mut self.ecs, &mut self.resources),
-            TurnState::PlayerTurn => {
-                self.player_systems
-                    .execute(&mut self.ecs, &mut self.resources);
+        // drain the event queue, watching for the window being closed
+        let mut close_requested = false;
+        while let Some(event) = INPUT.lock().pop() {
+            if let BEvent::CloseRequested = event {
+                close_requested = true;
             }
-            TurnState::MonsterTurn => {
-                self.monster_systems
-                    .execute(&mut self.ecs, &mut self.resources);
+        }
+        if close_requested || ctx.key == Some(VirtualKeyCode::Escape) {
+            // watching a replay never touches the saved game
+            if self.replay.is_some() {
+                ctx.quit();
+            } else {
+                self.save_and_quit(ctx);
             }
+            return;
+        }
+        if let Some(reloaded) = self.template_watcher.as_mut().and_then(|w| w.poll()) {
+            self.game.reload_templates(reloaded, self.respawn_on_reload);
+        }
+        ctx.set_active_console(0);
+        self.game
+            .resources
+            .insert(Point::from_tuple(ctx.mouse_pos()));
+        let current_state = self.game.turn_state();
+        match current_state {
             TurnState::GameOver => self.game_over(ctx),
             TurnState::Victory => self.victory(ctx),
-            TurnState::NextLevel => {
-                self.advance_level();
+            TurnState::MainMenu => self.main_menu(ctx),
+            _ if self.log_history.is_some() => self.message_log(ctx),
+            _ if ctx.key == Some(VirtualKeyCode::L) => {
+                // opened this tick, so the key is not also taken to close it
+                let history = LogHistory::new();
+                history.render(ctx, &self.game.resources.get::<GameLog>().unwrap());
+                self.log_history = Some(history);
             }
+            _ => {
+                let turns = self.game.turns();
+                let key = match &mut self.replay {
+                    Some(player) => player.next_key(
+                        ctx,
+                        turns,
+                        current_state == TurnState::AwaitingInput,
+                        &mut self.game.resources.get_mut::<GameLog>().unwrap(),
+                    ),
+                    None => ctx.key,
+                };
+                self.game.step(key);
+                if current_state == TurnState::NextLevel {
+                    self.report_vaults();
+                }
+                self.game.render();
+                // keep the replay file up to date, so it survives a crash
+                if self.replay.is_none() && self.game.turns() != turns {
+                    if let Err(e) = self
+                        .game
+                        .resources
+                        .get::<Replay>()
+                        .unwrap()
+                        .save(REPLAY_FILE)
+                    {
+                        println!("Unable to write the replay: {}", e);
+                    }
+                }
+            }
+        }
+        if let Some(player) = &self.replay {
+            player.render(ctx);
+        }
+        // permadeath: once the game has ended there is nothing left to continue
+        let new_state = self.game.turn_state();
+        if self.replay.is_none()
+            && new_state != current_state
+            && (new_state == TurnState::GameOver || new_state == TurnState::Victory)
+        {
+            discard_save();
         }
         render_draw_buffer(ctx).expect("Render error");
     }
 }
 
+/// Deletes the saved game, reporting rather than stopping if it can't, e.g.
+/// because the file is read-only.
+fn discard_save() {
+    if let Err(e) = delete_save() {
+        println!("Unable to delete the saved game: {}", e);
+    }
+}
+
+/// Finds the value following a command line flag, such as the `42` in `--seed 42`.
+/// * `args` - the command line arguments
+/// * `flag` - the flag to look for
+fn arg_value(args: &[String], flag: &str) -> Option<String> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|i| args.get(i + 1).cloned())
+}
+
+/// Reads the run seed from the `--seed` command line argument, falling back to
+/// the `DUNGEONCRAWL_SEED` environment variable.
+fn seed_from_args(args: &[String]) -> Option<u64> {
+    arg_value(args, "--seed")
+        .or_else(|| env::var("DUNGEONCRAWL_SEED").ok())
+        .map(|seed| parse_seed(&seed))
+}
+
+/// Reads the map size from the `--map-size` command line argument, such as
+/// `--map-size 200x200`, defaulting to the size of the screen.
+fn map_size_from_args(args: &[String]) -> (i32, i32) {
+    let size = match arg_value(args, "--map-size") {
+        Some(size) => size,
+        None => return (SCREEN_WIDTH, SCREEN_HEIGHT),
+    };
+    let parse = |n: &str| {
+        n.parse::<i32>()
+            .ok()
+            .filter(|n| *n >= MIN_MAP_SIZE)
+            .unwrap_or_else(|| {
+                panic!(
+                    "Invalid --map-size, each side must be at least {}",
+                    MIN_MAP_SIZE
+                )
+            })
+    };
+    match size.split_once('x') {
+        Some((width, height)) => (parse(width), parse(height)),
+        None => panic!("Invalid --map-size, expected WIDTHxHEIGHT"),
+    }
+}
+
 /// This is the `main` function.
 fn main() -> BError {
     // set RUST_BACKTRACE environment variable
     env::set_var("RUST_BACKTRACE", "full");
+    let args: Vec<String> = env::args().collect();
+    if args.iter().any(|arg| arg == "--headless") {
+        let options = HeadlessOptions {
+            seed: seed_from_args(&args),
+            games: arg_value(&args, "--games").map_or(1, |n| n.parse().expect("Invalid --games")),
+            max_turns: arg_value(&args, "--max-turns")
+                .map_or(1000, |n| n.parse().expect("Invalid --max-turns")),
+            map_size: map_size_from_args(&args),
+            script: arg_value(&args, "--script"),
+            replay: arg_value(&args, "--replay"),
+        };
+        if let Err(e) = run_headless(&options) {
+            eprintln!("Headless run failed: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    // broken data files are reported before the window opens
+    let data = match GameData::load() {
+        Ok(data) => data,
+        Err(e) => {
+            eprintln!("Unable to load the game data: {}", e);
+            std::process::exit(1);
+        }
+    };
     // use new to create a generic terminal and specify attributes directly
     let context = BTermBuilder::new()
         .with_title("Rusty Roguelike")
         // fps_cap automatically tracks game speed and tells the OS it can rest in between frames
         .with_fps_cap(30.0)
+        // advanced input queues events, letting the game save when the window is closed
+        .with_advanced_input(true)
         // with_dimensions specifies the size of subsequent consoles
         .with_dimensions(DISPLAY_WIDTH, DISPLAY_HEIGHT)
         // the tile dimensions are the size of each character in the font file
@@ -261,5 +370,54 @@ fn main() -> BError {
         .with_simple_console_no_bg(SCREEN_WIDTH * 2, SCREEN_HEIGHT * 2, "terminal8x8.png")
         .build()?;
 
-    main_loop(context, State::new())
+    // watch a level being generated instead of playing, see the readme
+    if args.iter().any(|arg| arg == "--mapgen-viewer") {
+        let game_rng = match seed_from_args(&args) {
+            Some(seed) => GameRng::new(seed),
+            None => GameRng::from_entropy(),
+        };
+        let depth: u32 =
+            arg_value(&args, "--depth").map_or(0, |n| n.parse().expect("Invalid --depth"));
+        let speed =
+            arg_value(&args, "--speed").map_or(4.0, |n| n.parse().expect("Invalid --speed"));
+        let (width, height) = map_size_from_args(&args);
+        println!(
+            "Generating dungeon level {} with seed {}",
+            depth + 1,
+            game_rng.seed
+        );
+        let mb = MapBuilder::recorded(&mut game_rng.map_stream(depth), width, height, depth, &data);
+        return main_loop(context, MapGenViewer::new(mb, speed));
+    }
+
+    let mut state = if let Some(path) = arg_value(&args, "--replay") {
+        let replay = Replay::load(&path).map_err(|e| format!("Unable to load {}: {}", path, e))?;
+        let speed =
+            arg_value(&args, "--speed").map_or(4.0, |n| n.parse().expect("Invalid --speed"));
+        let player = ReplayPlayer::new(replay, speed);
+        State {
+            game: player.new_game(data),
+            replay: Some(player),
+            log_history: None,
+            template_watcher: None,
+            respawn_on_reload: false,
+            debug_vaults: false,
+        }
+    } else {
+        State::new(data, seed_from_args(&args), map_size_from_args(&args))
+    };
+    // dev mode reloads the templates as they are edited, see the readme
+    let respawn = args.iter().any(|arg| arg == "--dev-respawn");
+    if respawn || args.iter().any(|arg| arg == "--dev") {
+        state.template_watcher = Some(TemplateWatcher::new(TEMPLATE_FILE));
+        state.respawn_on_reload = respawn;
+    }
+    if args.iter().any(|arg| arg == "--debug-vaults") {
+        state.debug_vaults = true;
+        state.report_vaults();
+    }
+    if state.replay.is_none() && has_save() {
+        state.game.resources.insert(TurnState::MainMenu);
+    }
+    main_loop(context, state)
 }
@@ -1,8 +1,6 @@
 use crate::prelude::*;
-/// The total number of tiles in the game screen.
-const NUM_TILES: usize = (SCREEN_WIDTH * SCREEN_HEIGHT) as usize;
 
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 /// Contains each possible tile type.
 pub enum TileType {
     /// Represents a wall tile.
@@ -10,37 +8,62 @@ pub enum TileType {
     /// Represents a floor tile.
     Floor,
     Exit,
+    /// Represents a closed door, which blocks sight until it is opened.
+    ClosedDoor,
+    /// Represents an open door, which can be walked and seen through.
+    OpenDoor,
 }
 
+impl TileType {
+    /// Determines if a tile can be walked on, at least once any door on it is opened.
+    pub fn is_walkable(self) -> bool {
+        matches!(
+            self,
+            TileType::Floor | TileType::Exit | TileType::ClosedDoor | TileType::OpenDoor
+        )
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 /// The game map.
 pub struct Map {
+    /// The width of the map in tiles.
+    pub width: i32,
+    /// The height of the map in tiles.
+    pub height: i32,
     /// The tile set.
     pub tiles: Vec<TileType>,
     pub revealed_tiles: Vec<bool>,
 }
 
-/// Calculates the tile index.
-/// * `y` - the vertical position of the tile
-/// * `x` - the horizontal position of the tile
-pub fn map_idx(x: i32, y: i32) -> usize {
-    ((y * SCREEN_WIDTH) + x) as usize
-}
-
 impl Map {
     /// Initialises the `Map`.
-    pub fn new() -> Self {
+    /// * `width` - the width of the map in tiles
+    /// * `height` - the height of the map in tiles
+    pub fn new(width: i32, height: i32) -> Self {
+        let num_tiles = (width * height) as usize;
         // create a map consisting entirely of floors
         Self {
-            tiles: vec![TileType::Floor; NUM_TILES],
-            revealed_tiles: vec![false; NUM_TILES],
+            width,
+            height,
+            tiles: vec![TileType::Floor; num_tiles],
+            revealed_tiles: vec![false; num_tiles],
         }
     }
 
-    /// Checks the location specified in point is within the screen boundaries.
+    /// Calculates the tile index.
+    /// * `&self` - allows access to the current `Map` instance
+    /// * `x` - the horizontal position of the tile
+    /// * `y` - the vertical position of the tile
+    pub fn map_idx(&self, x: i32, y: i32) -> usize {
+        ((y * self.width) + x) as usize
+    }
+
+    /// Checks the location specified in point is within the map boundaries.
     /// * `&self` - allows access to the current `Map` instance
     /// * `point` - the 2D position of the tile
     pub fn in_bounds(&self, point: Point) -> bool {
-        point.x >= 0 && point.x < SCREEN_WIDTH && point.y >= 0 && point.y < SCREEN_HEIGHT
+        point.x >= 0 && point.x < self.width && point.y >= 0 && point.y < self.height
     }
 
     /// Determines if the player can enter a tile.
@@ -48,8 +71,21 @@ impl Map {
     /// * `point` - the 2D position of the tile
     pub fn can_enter_tile(&self, point: Point) -> bool {
         self.in_bounds(point)
-            && (self.tiles[map_idx(point.x, point.y)] == TileType::Floor
-                || self.tiles[map_idx(point.x, point.y)] == TileType::Exit)
+            && matches!(
+                self.tiles[self.map_idx(point.x, point.y)],
+                TileType::Floor | TileType::Exit | TileType::OpenDoor
+            )
+    }
+
+    /// Determines if there is a door at a location, and if so whether it is open.
+    /// * `&self` - allows access to the current `Map` instance
+    /// * `point` - the 2D position of the tile
+    pub fn door_at(&self, point: Point) -> Option<bool> {
+        match self.try_idx(point).map(|idx| self.tiles[idx]) {
+            Some(TileType::OpenDoor) => Some(true),
+            Some(TileType::ClosedDoor) => Some(false),
+            _ => None,
+        }
     }
 
     /// Determines a tile's index coordinates.
@@ -59,7 +95,7 @@ impl Map {
         if !self.in_bounds(point) {
             None
         } else {
-            Some(map_idx(point.x, point.y))
+            Some(self.map_idx(point.x, point.y))
         }
     }
 
@@ -69,8 +105,9 @@ impl Map {
         let destination = loc + delta;
         // check that the destination is on the map
         if self.in_bounds(destination) {
-            // determine if you can enter the tile
-            if self.can_enter_tile(destination) {
+            // determine if you can enter the tile, counting closed doors as
+            // they can be opened on the way
+            if self.tiles[self.point2d_to_index(destination)].is_walkable() {
                 // if you can enter the tile determine its array index and return Some(idx)
                 let idx = self.point2d_to_index(destination);
                 Some(idx)
@@ -110,13 +147,13 @@ impl BaseMap for Map {
     }
 
     fn is_opaque(&self, idx: usize) -> bool {
-        self.tiles[idx as usize] != TileType::Floor
+        !matches!(self.tiles[idx], TileType::Floor | TileType::OpenDoor)
     }
 }
 
 impl Algorithm2D for Map {
     fn dimensions(&self) -> Point {
-        Point::new(SCREEN_WIDTH, SCREEN_HEIGHT)
+        Point::new(self.width, self.height)
     }
 
     fn in_bounds(&self, point: Point) -> bool {
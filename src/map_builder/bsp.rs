@@ -0,0 +1,132 @@
+use super::MapArchitect;
+use crate::prelude::*;
+
+/// The smallest width or height a leaf can be cut down to.
+const MIN_LEAF: i32 = 8;
+/// Leaves wider or taller than this are always cut in two.
+const MAX_LEAF: i32 = 20;
+/// The smallest width or height of a room.
+const MIN_ROOM: i32 = 3;
+
+/// Cuts the map in two again and again, puts a room in every leaf and joins
+/// each pair of halves with a corridor, so every part of the map is used.
+pub struct BspArchitect {}
+
+impl MapArchitect for BspArchitect {
+    fn build(&mut self, rng: &mut RandomNumberGenerator, mb: &mut MapBuilder) {
+        let whole_map = Rect::with_size(0, 0, mb.map.width, mb.map.height);
+        mb.rooms = self.partition(whole_map, rng, mb);
+        mb.player_start = mb.rooms[0].center();
+    }
+
+    fn name(&self) -> &'static str {
+        "Bsp"
+    }
+}
+
+impl BspArchitect {
+    /// Carves the rooms of a leaf, or cuts it in two and carves both halves,
+    /// returning the rooms carved.
+    /// * `leaf` - the part of the map to fill
+    /// * `rng` - the level's map generation stream
+    /// * `mb` - the level being built
+    fn partition(
+        &mut self,
+        leaf: Rect,
+        rng: &mut RandomNumberGenerator,
+        mb: &mut MapBuilder,
+    ) -> Vec<Rect> {
+        let (width, height) = (leaf.width(), leaf.height());
+        let can_cut_across = width >= MIN_LEAF * 2;
+        let can_cut_down = height >= MIN_LEAF * 2;
+        // large leaves are always cut, the rest now and then so rooms vary in size
+        let cut = (can_cut_across || can_cut_down)
+            && (width > MAX_LEAF || height > MAX_LEAF || rng.range(0, 4) != 0);
+        if !cut {
+            return vec![self.carve_room(leaf, rng, mb)];
+        }
+
+        // cut long leaves across their length, square ones either way
+        let across = match (can_cut_across, can_cut_down) {
+            (true, true) if width * 4 > height * 5 => true,
+            (true, true) if height * 4 > width * 5 => false,
+            (true, true) => rng.range(0, 2) == 0,
+            (across, _) => across,
+        };
+        let (first, second) = if across {
+            let at = rng.range(MIN_LEAF, width - MIN_LEAF + 1);
+            (
+                Rect::with_size(leaf.x1, leaf.y1, at, height),
+                Rect::with_size(leaf.x1 + at, leaf.y1, width - at, height),
+            )
+        } else {
+            let at = rng.range(MIN_LEAF, height - MIN_LEAF + 1);
+            (
+                Rect::with_size(leaf.x1, leaf.y1, width, at),
+                Rect::with_size(leaf.x1, leaf.y1 + at, width, height - at),
+            )
+        };
+        let mut rooms = self.partition(first, rng, mb);
+        let second_rooms = self.partition(second, rng, mb);
+        self.join(&rooms, &second_rooms, rng, mb);
+        rooms.extend(second_rooms);
+        rooms
+    }
+
+    /// Carves a room of random size somewhere in a leaf, leaving a wall around it.
+    /// * `leaf` - the leaf to put the room in
+    /// * `rng` - the level's map generation stream
+    /// * `mb` - the level being built
+    fn carve_room(
+        &mut self,
+        leaf: Rect,
+        rng: &mut RandomNumberGenerator,
+        mb: &mut MapBuilder,
+    ) -> Rect {
+        let width = rng.range(MIN_ROOM, leaf.width() - 1);
+        let height = rng.range(MIN_ROOM, leaf.height() - 1);
+        let room = Rect::with_size(
+            rng.range(leaf.x1 + 1, leaf.x2 - width),
+            rng.range(leaf.y1 + 1, leaf.y2 - height),
+            width,
+            height,
+        );
+        room.for_each(|p| {
+            let idx = mb.map.point2d_to_index(p);
+            mb.map.tiles[idx] = TileType::Floor;
+        });
+        mb.snapshot(format!("room at ({}, {})", room.x1, room.y1));
+        room
+    }
+
+    /// Digs a corridor between the closest pair of rooms from two sibling leaves.
+    /// * `first` - the rooms of one leaf
+    /// * `second` - the rooms of the other
+    /// * `rng` - the level's map generation stream
+    /// * `mb` - the level being built
+    fn join(
+        &mut self,
+        first: &[Rect],
+        second: &[Rect],
+        rng: &mut RandomNumberGenerator,
+        mb: &mut MapBuilder,
+    ) {
+        let distance = |(a, b): &(Point, Point)| DistanceAlg::Manhattan.distance2d(*a, *b);
+        let (from, to) = first
+            .iter()
+            .flat_map(|a| second.iter().map(move |b| (a.center(), b.center())))
+            .min_by(|a, b| distance(a).partial_cmp(&distance(b)).unwrap())
+            .unwrap();
+        if rng.range(0, 2) == 1 {
+            mb.apply_horizontal_tunnel(from.x, to.x, from.y);
+            mb.apply_vertical_tunnel(from.y, to.y, to.x);
+        } else {
+            mb.apply_vertical_tunnel(from.y, to.y, from.x);
+            mb.apply_horizontal_tunnel(from.x, to.x, to.y);
+        }
+        mb.snapshot(format!(
+            "corridor from ({}, {}) to ({}, {})",
+            from.x, from.y, to.x, to.y
+        ));
+    }
+}
@@ -2,61 +2,64 @@ use super::MapArchitect;
 use crate::prelude::*;
 
 const STAGGER_DISTANCE: usize = 400;
-const NUM_TILES: usize = (SCREEN_WIDTH * SCREEN_HEIGHT) as usize;
-const DESIRED_FLOOR: usize = NUM_TILES / 3;
 pub struct DrunkardsWalkArchitect {}
 
 impl MapArchitect for DrunkardsWalkArchitect {
-    fn new(&mut self, rng: &mut RandomNumberGenerator) -> MapBuilder {
-        let mut mb = MapBuilder {
-            map: Map::new(),
-            rooms: Vec::new(),
-            monster_spawns: Vec::new(),
-            player_start: Point::zero(),
-            amulet_start: Point::zero(),
-            theme: super::themes::DungeonTheme::new(),
-        };
-
-        mb.fill(TileType::Wall);
-        let center = Point::new(SCREEN_WIDTH / 2, SCREEN_HEIGHT / 2);
+    fn build(&mut self, rng: &mut RandomNumberGenerator, mb: &mut MapBuilder) {
+        let desired_floor = mb.map.tiles.len() / 3;
+        let (width, height) = (mb.map.width, mb.map.height);
+        let center = Point::new(width / 2, height / 2);
         self.drunkard(&center, rng, &mut mb.map);
+        mb.snapshot(format!("walk 1 from ({}, {})", center.x, center.y));
+        let mut walks = 1;
         while mb
             .map
             .tiles
             .iter()
             .filter(|t| **t == TileType::Floor)
             .count()
-            < DESIRED_FLOOR
+            < desired_floor
         {
-            self.drunkard(
-                &Point::new(rng.range(0, SCREEN_WIDTH), rng.range(0, SCREEN_HEIGHT)),
-                rng,
-                &mut mb.map,
-            );
+            let start = Point::new(rng.range(0, width), rng.range(0, height));
+            self.drunkard(&start, rng, &mut mb.map);
+            walks += 1;
+            mb.snapshot(format!("walk {} from ({}, {})", walks, start.x, start.y));
             let dijkstra_map = DijkstraMap::new(
-                SCREEN_WIDTH,
-                SCREEN_HEIGHT,
-                &vec![mb.map.point2d_to_index(center)],
+                width,
+                height,
+                &[mb.map.point2d_to_index(center)],
                 &mb.map,
                 1024.0,
             );
-            dijkstra_map
+            // floor that can't be reached from the centre is walled over
+            let unreachable: Vec<usize> = dijkstra_map
                 .map
                 .iter()
                 .enumerate()
-                .filter(|(_, distance)| *distance > &2000.0)
-                .for_each(|(idx, _)| mb.map.tiles[idx] = TileType::Wall);
+                .filter(|(idx, distance)| {
+                    *distance > &2000.0 && mb.map.tiles[*idx] == TileType::Floor
+                })
+                .map(|(idx, _)| idx)
+                .collect();
+            unreachable
+                .iter()
+                .for_each(|idx| mb.map.tiles[*idx] = TileType::Wall);
+            mb.snapshot(format!(
+                "walled off {} floor tiles out of reach of the centre",
+                unreachable.len()
+            ));
         }
-        mb.monster_spawns = mb.spawn_monsters(&center, rng);
         mb.player_start = center;
-        mb.amulet_start = mb.find_most_distant();
-        mb
+    }
+
+    fn name(&self) -> &'static str {
+        "DrunkardsWalk"
     }
 }
 
 impl DrunkardsWalkArchitect {
     fn drunkard(&mut self, start: &Point, rng: &mut RandomNumberGenerator, map: &mut Map) {
-        let mut drunkard_pos = start.clone();
+        let mut drunkard_pos = *start;
         let mut distance_staggered = 0;
         loop {
             let drunk_idx = map.point2d_to_index(drunkard_pos);
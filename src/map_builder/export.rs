@@ -0,0 +1,280 @@
+use crate::prelude::*;
+use image::{imageops, Rgba, RgbaImage};
+
+/// The font the game draws maps with, 16 glyphs to a row.
+pub const FONT_FILE: &str = "resources/dungeonfont.png";
+
+/// The character shown for a random monster or item spawn, as in vault layouts.
+const SPAWN_CHAR: char = 'M';
+/// How strongly random spawns are tinted red in images, from 0 to 1.
+const SPAWN_TINT: f32 = 0.4;
+
+/// Writes a generated level out as text, JSON or an image, so levels can be
+/// reviewed and compared between versions without starting the game.
+pub struct MapExport<'a> {
+    mb: &'a MapBuilder,
+    /// The dungeon level, starting at 0, which decides between an exit and the amulet.
+    depth: u32,
+    /// The templates the level's vault spawns name.
+    templates: &'a Templates,
+}
+
+impl<'a> MapExport<'a> {
+    /// Prepares a level for export.
+    /// * `mb` - the generated level
+    /// * `depth` - the dungeon level, starting at 0
+    /// * `templates` - the templates the level's vault spawns name
+    pub fn new(mb: &'a MapBuilder, depth: u32, templates: &'a Templates) -> Self {
+        Self {
+            mb,
+            depth,
+            templates,
+        }
+    }
+
+    /// The map as text, one line per row: `#` for walls, `.` for floors, `+`
+    /// and `'` for closed and open doors, `>` for the exit, `|` for the amulet,
+    /// `@` for the player start, `M` for random spawns and each template's own
+    /// glyph for vault spawns.
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        for y in 0..self.mb.map.height {
+            for x in 0..self.mb.map.width {
+                let pt = Point::new(x, y);
+                let c = match self.entity_at(pt) {
+                    Some((c, _)) => c,
+                    None if self.mb.monster_spawns.contains(&pt) => SPAWN_CHAR,
+                    None => self.tile_char(pt),
+                };
+                text.push(c);
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    /// The level as a JSON document, with the tiles as rows of text and
+    /// everything else as lists of positions.
+    pub fn json(&self) -> String {
+        let mb = self.mb;
+        // the last level has the amulet where the others have their exit
+        let (exit, amulet) = if self.depth == FINAL_LEVEL {
+            (None, Some(mb.amulet_start))
+        } else {
+            (Some(mb.amulet_start), None)
+        };
+        let level = JsonLevel {
+            width: mb.map.width,
+            height: mb.map.height,
+            depth: self.depth,
+            theme: mb.theme.name(),
+            tiles: (0..mb.map.height)
+                .map(|y| {
+                    (0..mb.map.width)
+                        .map(|x| self.tile_char(Point::new(x, y)))
+                        .collect()
+                })
+                .collect(),
+            rooms: &mb.rooms,
+            player_start: mb.player_start,
+            exit,
+            amulet,
+            monster_spawns: &mb.monster_spawns,
+            entity_spawns: mb
+                .entity_spawns
+                .iter()
+                .map(|(pt, name)| JsonSpawn {
+                    x: pt.x,
+                    y: pt.y,
+                    name,
+                })
+                .collect(),
+            vaults: mb
+                .placed_vaults
+                .iter()
+                .map(|vault| JsonVault {
+                    name: &vault.name,
+                    area: vault.area,
+                    orientation: vault.orientation.to_string(),
+                })
+                .collect(),
+        };
+        // pretty printed, one value per line, so exported maps diff line by line
+        let mut json =
+            serde_json::to_string_pretty(&level).expect("A level can always be written as JSON");
+        json.push('\n');
+        json
+    }
+
+    /// The map drawn with the game's font in the level's theme, every tile
+    /// shown as if in view. Random spawns are tinted red, as what they hold
+    /// is only decided when the level is populated.
+    /// * `font` - the font image, usually `FONT_FILE`
+    /// * `tile_size` - the width and height of a tile in the image, in pixels
+    pub fn png(&self, font: &str, tile_size: u32) -> Result<RgbaImage, DataFileError> {
+        let font = image::open(font)
+            .map_err(|e| DataFileError::new(font, e))?
+            .to_rgba8();
+        let font = imageops::resize(
+            &font,
+            tile_size * 16,
+            tile_size * 16,
+            imageops::FilterType::Triangle,
+        );
+        let map = &self.mb.map;
+        let mut image = RgbaImage::new(map.width as u32 * tile_size, map.height as u32 * tile_size);
+        let theme = &self.mb.theme;
+        for y in 0..map.height {
+            for x in 0..map.width {
+                let pt = Point::new(x, y);
+                let tile = self.tile_at(pt);
+                let colors = theme.tile_colors(tile, true);
+                let area = TileArea::new(pt, tile_size);
+                area.fill(&mut image, colors.bg, 1.0);
+                area.draw(&mut image, &font, theme.tile_glyph(tile, pt), colors.fg);
+                if self.mb.monster_spawns.contains(&pt) {
+                    area.fill(&mut image, RGBA::named(RED), SPAWN_TINT);
+                }
+                if let Some((c, name)) = self.entity_at(pt) {
+                    let fg = theme
+                        .entity_colors(name)
+                        .map_or(RGBA::named(WHITE), |colors| colors.fg);
+                    area.draw(&mut image, &font, to_cp437(c), fg);
+                }
+            }
+        }
+        Ok(image)
+    }
+
+    // The tile at a position, with the exit the game adds to every level but the last.
+    fn tile_at(&self, pt: Point) -> TileType {
+        if pt == self.mb.amulet_start && self.depth != FINAL_LEVEL {
+            TileType::Exit
+        } else {
+            self.mb.map.tiles[self.mb.map.point2d_to_index(pt)]
+        }
+    }
+
+    fn tile_char(&self, pt: Point) -> char {
+        match self.tile_at(pt) {
+            TileType::Wall => '#',
+            TileType::Floor => '.',
+            TileType::Exit => '>',
+            TileType::ClosedDoor => '+',
+            TileType::OpenDoor => '\'',
+        }
+    }
+
+    // The character and name of the player, the amulet or a vault spawn at a position.
+    fn entity_at(&self, pt: Point) -> Option<(char, &str)> {
+        if pt == self.mb.player_start {
+            return Some(('@', "Player"));
+        }
+        if pt == self.mb.amulet_start && self.depth == FINAL_LEVEL {
+            return Some(('|', "Amulet of Yala"));
+        }
+        let (_, name) = self.mb.entity_spawns.iter().find(|(p, _)| *p == pt)?;
+        let template = self.templates.entities.iter().find(|t| &t.name == name);
+        Some((template.map_or('?', |t| t.glyph), name))
+    }
+}
+
+/// A level as written by `MapExport::json`.
+#[derive(Serialize)]
+struct JsonLevel<'a> {
+    width: i32,
+    height: i32,
+    depth: u32,
+    theme: &'a str,
+    tiles: Vec<String>,
+    rooms: &'a [Rect],
+    player_start: Point,
+    /// The exit, on every level but the last.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exit: Option<Point>,
+    /// The Amulet of Yala, on the last level.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    amulet: Option<Point>,
+    monster_spawns: &'a [Point],
+    entity_spawns: Vec<JsonSpawn<'a>>,
+    vaults: Vec<JsonVault<'a>>,
+}
+
+/// A template spawned by a vault.
+#[derive(Serialize)]
+struct JsonSpawn<'a> {
+    x: i32,
+    y: i32,
+    name: &'a str,
+}
+
+/// A vault placed on the level.
+#[derive(Serialize)]
+struct JsonVault<'a> {
+    name: &'a str,
+    /// The footprint, as `x1`, `y1`, `x2` and `y2` alongside the name.
+    #[serde(flatten)]
+    area: Rect,
+    orientation: String,
+}
+
+/// The square of the exported image one tile is drawn in.
+struct TileArea {
+    left: u32,
+    top: u32,
+    size: u32,
+}
+
+impl TileArea {
+    fn new(pt: Point, size: u32) -> Self {
+        Self {
+            left: pt.x as u32 * size,
+            top: pt.y as u32 * size,
+            size,
+        }
+    }
+
+    /// Covers the square in a color.
+    /// * `opacity` - from 0 to 1, less than 1 to tint what is already drawn
+    fn fill(&self, image: &mut RgbaImage, color: RGBA, opacity: f32) {
+        for y in self.top..self.top + self.size {
+            for x in self.left..self.left + self.size {
+                blend(image.get_pixel_mut(x, y), color, opacity);
+            }
+        }
+    }
+
+    /// Draws a glyph from the font in a color, as the game's consoles do.
+    fn draw(&self, image: &mut RgbaImage, font: &RgbaImage, glyph: FontCharType, color: RGBA) {
+        let font_left = (glyph as u32 % 16) * self.size;
+        let font_top = (glyph as u32 / 16) * self.size;
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let texel = font.get_pixel(font_left + x, font_top + y);
+                let tinted = RGBA::from_f32(
+                    texel[0] as f32 / 255.0 * color.r,
+                    texel[1] as f32 / 255.0 * color.g,
+                    texel[2] as f32 / 255.0 * color.b,
+                    1.0,
+                );
+                let opacity = texel[3] as f32 / 255.0 * color.a;
+                blend(
+                    image.get_pixel_mut(self.left + x, self.top + y),
+                    tinted,
+                    opacity,
+                );
+            }
+        }
+    }
+}
+
+// Mixes a color into a pixel, leaving the pixel opaque.
+fn blend(pixel: &mut Rgba<u8>, color: RGBA, opacity: f32) {
+    let mix = |old: u8, new: f32| (old as f32 * (1.0 - opacity) + new * 255.0 * opacity) as u8;
+    *pixel = Rgba([
+        mix(pixel[0], color.r),
+        mix(pixel[1], color.g),
+        mix(pixel[2], color.b),
+        255,
+    ]);
+}
@@ -0,0 +1,25 @@
+use super::prefab::stamp;
+use super::MapArchitect;
+use crate::prelude::*;
+
+/// Builds a level from a hand-made layout, see `LAYOUT_DIR`, centered on a map of walls.
+pub struct FixedLayoutArchitect {
+    pub layout: Vault,
+}
+
+impl MapArchitect for FixedLayoutArchitect {
+    fn build(&mut self, _rng: &mut RandomNumberGenerator, mb: &mut MapBuilder) {
+        // layouts are checked to fit the smallest map when the dungeon plan is loaded
+        let area = Rect::with_size(
+            (mb.map.width - self.layout.width()) / 2,
+            (mb.map.height - self.layout.height()) / 2,
+            self.layout.width(),
+            self.layout.height(),
+        );
+        stamp(mb, &self.layout, area, Orientation::default());
+    }
+
+    fn name(&self) -> &'static str {
+        "FixedLayout"
+    }
+}
@@ -0,0 +1,136 @@
+use super::MapArchitect;
+use crate::prelude::*;
+
+/// Carves a maze of one tile wide corridors, then opens some of its dead ends
+/// into loops so there is more than one way around.
+pub struct MazeArchitect {
+    /// The share of dead ends, in percent, knocked through into a neighbouring
+    /// corridor. 0 gives a perfect maze, with exactly one path between any two tiles.
+    pub loop_percent: i32,
+}
+
+impl MapArchitect for MazeArchitect {
+    fn build(&mut self, rng: &mut RandomNumberGenerator, mb: &mut MapBuilder) {
+        self.carve(rng, mb);
+        mb.snapshot("carved a perfect maze");
+        self.braid(rng, mb);
+        mb.snapshot(format!("opened {}% of the dead ends", self.loop_percent));
+
+        // the two ends of the longest path through the maze
+        mb.player_start = Point::new(1, 1);
+        mb.player_start = mb.find_most_distant();
+        mb.amulet_start = mb.find_most_distant();
+
+        // monsters lurk in the dead ends, which the spawn step keeps
+        let start = mb.player_start;
+        let amulet = mb.amulet_start;
+        let mut dead_ends: Vec<Point> = mb
+            .spawnable_tiles(&start)
+            .into_iter()
+            .filter(|pt| *pt != amulet && self.is_dead_end(&mb.map, *pt))
+            .collect();
+        while mb.monster_spawns.len() < mb.monster_count() {
+            match rng.random_slice_index(&dead_ends) {
+                Some(index) => mb.monster_spawns.push(dead_ends.remove(index)),
+                None => break,
+            }
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "Maze"
+    }
+}
+
+impl MazeArchitect {
+    /// The maze cells next to a cell: two tiles away, with a wall in between.
+    /// * `map` - the map being carved
+    /// * `cell` - the cell, at odd coordinates
+    fn neighbours(&self, map: &Map, cell: Point) -> Vec<Point> {
+        [(0, -2), (2, 0), (0, 2), (-2, 0)]
+            .iter()
+            .map(|(dx, dy)| Point::new(cell.x + dx, cell.y + dy))
+            .filter(|pt| pt.x > 0 && pt.y > 0 && pt.x < map.width - 1 && pt.y < map.height - 1)
+            .collect()
+    }
+
+    /// Turns a cell, and the wall between it and the previous cell, into floor.
+    /// * `map` - the map being carved
+    /// * `from` - the cell the corridor comes from
+    /// * `to` - the cell the corridor goes to
+    fn open(&self, map: &mut Map, from: Point, to: Point) {
+        let between = Point::new((from.x + to.x) / 2, (from.y + to.y) / 2);
+        let (between_idx, to_idx) = (map.point2d_to_index(between), map.point2d_to_index(to));
+        map.tiles[between_idx] = TileType::Floor;
+        map.tiles[to_idx] = TileType::Floor;
+    }
+
+    /// Carves a perfect maze with a randomised depth-first search from the top left cell.
+    /// * `rng` - the level's map generation stream
+    /// * `mb` - the level being built
+    fn carve(&self, rng: &mut RandomNumberGenerator, mb: &mut MapBuilder) {
+        let first = Point::new(1, 1);
+        let first_idx = mb.map.point2d_to_index(first);
+        mb.map.tiles[first_idx] = TileType::Floor;
+        let mut stack = vec![first];
+        while let Some(cell) = stack.last().copied() {
+            let unvisited: Vec<Point> = self
+                .neighbours(&mb.map, cell)
+                .into_iter()
+                .filter(|pt| mb.map.tiles[mb.map.point2d_to_index(*pt)] == TileType::Wall)
+                .collect();
+            match rng.random_slice_entry(&unvisited) {
+                Some(next) => {
+                    self.open(&mut mb.map, cell, *next);
+                    stack.push(*next);
+                }
+                None => {
+                    stack.pop();
+                }
+            }
+        }
+    }
+
+    /// Knocks `loop_percent` of the dead ends through into another corridor.
+    /// * `rng` - the level's map generation stream
+    /// * `mb` - the level being built
+    fn braid(&self, rng: &mut RandomNumberGenerator, mb: &mut MapBuilder) {
+        let dead_ends: Vec<Point> = (0..mb.map.tiles.len())
+            .map(|idx| mb.map.index_to_point2d(idx))
+            .filter(|pt| pt.x % 2 == 1 && pt.y % 2 == 1 && self.is_dead_end(&mb.map, *pt))
+            .collect();
+        for cell in dead_ends {
+            // an earlier loop may already have opened this one up
+            if !self.is_dead_end(&mb.map, cell) || rng.range(0, 100) >= self.loop_percent {
+                continue;
+            }
+            let walled_off: Vec<Point> = self
+                .neighbours(&mb.map, cell)
+                .into_iter()
+                .filter(|next| {
+                    let between = Point::new((cell.x + next.x) / 2, (cell.y + next.y) / 2);
+                    mb.map.tiles[mb.map.point2d_to_index(between)] == TileType::Wall
+                })
+                .collect();
+            if let Some(next) = rng.random_slice_entry(&walled_off) {
+                self.open(&mut mb.map, cell, *next);
+            }
+        }
+    }
+
+    /// Returns true if a floor tile has only one way out.
+    /// * `map` - the map being carved
+    /// * `pt` - the tile to check
+    fn is_dead_end(&self, map: &Map, pt: Point) -> bool {
+        let floor = |x: i32, y: i32| {
+            map.try_idx(Point::new(x, y))
+                .is_some_and(|idx| map.tiles[idx] == TileType::Floor)
+        };
+        floor(pt.x, pt.y)
+            && [(0, -1), (1, 0), (0, 1), (-1, 0)]
+                .iter()
+                .filter(|(dx, dy)| floor(pt.x + dx, pt.y + dy))
+                .count()
+                == 1
+    }
+}
@@ -1,27 +1,162 @@
 use crate::prelude::*;
 mod automata;
+mod bsp;
 mod drunkard;
 mod empty;
+mod export;
+mod fixed;
+mod maze;
+mod pipeline;
+mod plan;
 mod prefab;
 mod rooms;
+mod steps;
 mod themes;
-use automata::CellularAutomataArchitect;
-use drunkard::DrunkardsWalkArchitect;
-// use empty::EmptyArchitect;
-use prefab::apply_prefab;
-use rooms::RoomsArchitect;
-use themes::*;
-
-trait MapArchitect {
-    fn new(&mut self, rng: &mut RandomNumberGenerator) -> MapBuilder;
+mod vault;
+mod voronoi;
+mod wfc;
+pub use automata::CellularAutomataArchitect;
+pub use bsp::BspArchitect;
+pub use drunkard::DrunkardsWalkArchitect;
+pub use empty::EmptyArchitect;
+pub use export::{MapExport, FONT_FILE};
+pub use fixed::FixedLayoutArchitect;
+pub use maze::MazeArchitect;
+pub use pipeline::{MapPipeline, MetaStep};
+pub use plan::{DungeonPlan, LevelPlan, VaultSet, LAYOUT_DIR, PLAN_FILE};
+pub use prefab::{apply_prefab, PlacedVault};
+pub use rooms::RoomsArchitect;
+pub use steps::{
+    AddDoors, AddRooms, ApplyTheme, ChooseSpawns, CullUnreachable, PlaceExit, PlaceStart, Prefabs,
+};
+use themes::DungeonTheme;
+pub use themes::{theme_by_name, DataTheme, Themes, THEME_DIR};
+pub use vault::{Orientation, Vault, VaultTile, Vaults, ARCHITECT_NAMES, VAULT_DIR};
+pub use voronoi::VoronoiArchitect;
+pub use wfc::WaveFunctionCollapseArchitect;
+
+/// Carves the initial map of a `MapPipeline`, which its meta steps then
+/// rework. Architects may also suggest a player start and add rooms.
+pub trait MapArchitect {
+    /// Carves the map, which starts out as all walls.
+    /// * `rng` - the level's map generation stream
+    /// * `mb` - the level being built
+    fn build(&mut self, rng: &mut RandomNumberGenerator, mb: &mut MapBuilder);
+    /// The name vault files use to allow this architect, see `ARCHITECT_NAMES`.
+    fn name(&self) -> &'static str;
+}
+
+/// Creates an architect from the name vault files and the dungeon plan use for it.
+/// * `name` - one of `ARCHITECT_NAMES`
+/// * `plan` - the level's plan, which configures some architects
+/// * `data` - the vaults and layouts some architects learn from
+pub fn architect_by_name(
+    name: &str,
+    plan: &LevelPlan,
+    data: &GameData,
+) -> Option<Box<dyn MapArchitect>> {
+    match name {
+        "Rooms" => Some(Box::new(RoomsArchitect {})),
+        "CellularAutomata" => Some(Box::new(CellularAutomataArchitect {})),
+        "DrunkardsWalk" => Some(Box::new(DrunkardsWalkArchitect {})),
+        "Empty" => Some(Box::new(EmptyArchitect {})),
+        "Bsp" => Some(Box::new(BspArchitect {})),
+        "Voronoi" => Some(Box::new(VoronoiArchitect {})),
+        "WaveFunctionCollapse" => Some(Box::new(WaveFunctionCollapseArchitect {
+            samples: plan.wfc_samples(data),
+        })),
+        "Maze" => Some(Box::new(MazeArchitect {
+            loop_percent: plan.maze_loop_percent,
+        })),
+        _ => None,
+    }
 }
 
 pub trait MapTheme: Sync + Send {
     fn tile_to_render(&self, tile_type: TileType) -> FontCharType;
+    /// The glyph drawn for a tile at a position. Themes with several variants
+    /// of a tile pick one by position, so the map looks the same every frame.
+    fn tile_glyph(&self, tile_type: TileType, _pt: Point) -> FontCharType {
+        self.tile_to_render(tile_type)
+    }
+    /// The colors a tile is drawn in, while in view or once only remembered.
+    fn tile_colors(&self, _tile_type: TileType, visible: bool) -> ColorPair {
+        if visible {
+            ColorPair::new(WHITE, BLACK)
+        } else {
+            ColorPair::new(DARK_GREY, BLACK)
+        }
+    }
+    /// The colors to draw an entity in, by name, in place of its own.
+    fn entity_colors(&self, _name: &str) -> Option<ColorPair> {
+        None
+    }
+    /// The name the theme is stored under in a saved game.
+    fn name(&self) -> &str;
 }
 
-/// Maximum number of rooms in the dungeon.
+/// Maximum number of rooms in a screen-sized dungeon, larger and smaller maps
+/// have proportionally more or fewer rooms.
 const NUM_ROOMS: usize = 20;
+/// How many random rooms are tried before settling for fewer than `NUM_ROOMS`.
+const ROOM_ATTEMPTS: usize = 1000;
+/// The smallest width or height a map can be built with.
+pub const MIN_MAP_SIZE: i32 = 20;
+/// The share of the map, in percent, that must be floor the player can reach,
+/// or the level is generated again, see `CullUnreachable`.
+pub const MIN_FLOOR_PERCENT: usize = 10;
+
+/// The map as it was after one phase of generation, for watching a level
+/// being built, see `MapBuilder::recorded`.
+#[derive(Clone)]
+pub struct MapSnapshot {
+    /// What the phase did, e.g. `iteration 3`.
+    pub label: String,
+    pub map: Map,
+}
+
+/// Which region each tile of a map belongs to, for architects that split the
+/// map into regions, so that each region spawns its own kinds of monster.
+#[derive(Clone, Debug, Default)]
+pub struct Regions {
+    /// The width of the map, to find a tile's index.
+    width: i32,
+    /// The region of every tile by index, walls included, so tiles changed
+    /// after the architect, e.g. by a vault, still have one.
+    of_tile: Vec<Option<usize>>,
+    count: usize,
+}
+
+impl Regions {
+    /// Records the region of every tile.
+    /// * `width` - the width of the map
+    /// * `of_tile` - the region of each tile by index, numbered from 0
+    pub fn new(width: i32, of_tile: Vec<Option<usize>>) -> Self {
+        let count = of_tile.iter().flatten().max().map_or(0, |last| last + 1);
+        Self {
+            width,
+            of_tile,
+            count,
+        }
+    }
+
+    /// The number of regions, none if the map isn't split up.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// The region a tile belongs to, if any.
+    /// * `pt` - the position of the tile
+    pub fn region_at(&self, pt: Point) -> Option<usize> {
+        if pt.x < 0 || pt.x >= self.width || pt.y < 0 {
+            return None;
+        }
+        self.of_tile
+            .get((pt.y * self.width + pt.x) as usize)
+            .copied()
+            .flatten()
+    }
+}
 
 /// The map builder.
 pub struct MapBuilder {
@@ -30,35 +165,168 @@ pub struct MapBuilder {
     /// The rooms to be generated.
     pub rooms: Vec<Rect>,
     pub monster_spawns: Vec<Point>,
+    /// The region of each tile, for architects that split the map into regions.
+    pub regions: Regions,
+    /// Entities placed by template name, e.g. by a vault's legend.
+    pub entity_spawns: Vec<(Point, String)>,
+    /// The vaults stamped onto the map, for debug output.
+    pub placed_vaults: Vec<PlacedVault>,
     /// The player start location.
     pub player_start: Point,
     pub amulet_start: Point,
     pub theme: Box<dyn MapTheme>,
+    /// The map after each phase of generation, or `None` unless the level is
+    /// being recorded.
+    pub snapshots: Option<Vec<MapSnapshot>>,
 }
 
 impl MapBuilder {
-    /// Initialises the `MapBuilder`.  
-    ///
-    /// The map generated is filled with walls, has a number of randomly generated
-    /// rooms and corridors and has a player start location.
+    /// Builds a dungeon level the way the dungeon plan describes it, by
+    /// running the level's architect and meta steps through a `MapPipeline`.
     ///  * `rng` - allows access to the RandomNumberGenerator from `bracket_random`
-    pub fn new(rng: &mut RandomNumberGenerator) -> Self {
-        let mut architect: Box<dyn MapArchitect> = match rng.range(0, 3) {
-            0 => Box::new(DrunkardsWalkArchitect {}),
-            1 => Box::new(RoomsArchitect {}),
-            _ => Box::new(CellularAutomataArchitect {}),
-        };
-        let mut mb = architect.new(rng);
-        apply_prefab(&mut mb, rng);
+    ///  * `width` - the width of the map, at least `MIN_MAP_SIZE`
+    ///  * `height` - the height of the map, at least `MIN_MAP_SIZE`
+    ///  * `depth` - the dungeon level being built, starting at 0
+    ///  * `data` - the dungeon plan, and the layouts, vaults and themes it chooses from
+    pub fn new(
+        rng: &mut RandomNumberGenerator,
+        width: i32,
+        height: i32,
+        depth: u32,
+        data: &GameData,
+    ) -> Self {
+        Self::build(rng, width, height, depth, data, false)
+    }
 
-        mb.theme = match rng.range(0, 2) {
-            0 => DungeonTheme::new(),
-            _ => ForestTheme::new(),
+    /// Builds a dungeon level exactly as `MapBuilder::new` does, keeping a
+    /// snapshot of the map after every phase in `MapBuilder::snapshots`.
+    ///  * `rng` - allows access to the RandomNumberGenerator from `bracket_random`
+    ///  * `width` - the width of the map, at least `MIN_MAP_SIZE`
+    ///  * `height` - the height of the map, at least `MIN_MAP_SIZE`
+    ///  * `depth` - the dungeon level being built, starting at 0
+    ///  * `data` - the dungeon plan, and the layouts, vaults and themes it chooses from
+    pub fn recorded(
+        rng: &mut RandomNumberGenerator,
+        width: i32,
+        height: i32,
+        depth: u32,
+        data: &GameData,
+    ) -> Self {
+        Self::build(rng, width, height, depth, data, true)
+    }
+
+    // Builds a level for `MapBuilder::new` or `MapBuilder::recorded`.
+    fn build(
+        rng: &mut RandomNumberGenerator,
+        width: i32,
+        height: i32,
+        depth: u32,
+        data: &GameData,
+        record: bool,
+    ) -> Self {
+        assert!(
+            width >= MIN_MAP_SIZE && height >= MIN_MAP_SIZE,
+            "Maps must be at least {0}x{0} tiles",
+            MIN_MAP_SIZE
+        );
+        let plan = data.plan.level(depth);
+        let architect: Box<dyn MapArchitect> = match &plan.layout {
+            // the plan is checked to only name layouts that exist
+            Some(name) => Box::new(FixedLayoutArchitect {
+                layout: data.layouts.by_name(name).unwrap().clone(),
+            }),
+            None if plan.architects.is_empty() => match rng.range(0, 4) {
+                0 => Box::new(DrunkardsWalkArchitect {}),
+                1 => Box::new(RoomsArchitect {}),
+                2 => Box::new(BspArchitect {}),
+                _ => Box::new(CellularAutomataArchitect {}),
+            },
+            None => {
+                let name = &plan.architects[rng.range(0, plan.architects.len())];
+                architect_by_name(name, plan, data).unwrap()
+            }
         };
+        let architect_name = architect.name();
+
+        let mut pipeline = MapPipeline::new(architect);
+        if record {
+            pipeline = pipeline.recording();
+        }
+        // a fixed layout places its start, exit and spawns exactly as drawn
+        if plan.layout.is_none() {
+            if plan.add_rooms {
+                pipeline = pipeline.with(AddRooms);
+            }
+            pipeline = pipeline
+                .with(AddDoors)
+                .with(PlaceStart)
+                .with(CullUnreachable)
+                .with(PlaceExit)
+                .with(ChooseSpawns {
+                    density: plan.monster_density,
+                });
+        }
+        pipeline = pipeline
+            .with(Prefabs {
+                vaults: &data.vaults,
+                depth,
+                architect: architect_name,
+                vault_set: &plan.vaults,
+            })
+            .with(ApplyTheme {
+                themes: &data.themes,
+                names: &plan.themes,
+            });
+        let mb = pipeline.build(rng, width, height);
+        let distances = prefab::distances_from_start(&mb);
+        assert!(
+            distances.map[mb.map.point2d_to_index(mb.amulet_start)] < f32::MAX,
+            "The {} architect left the exit at {:?} out of reach of the start at {:?}",
+            architect_name,
+            mb.amulet_start,
+            mb.player_start
+        );
+        mb
+    }
 
+    /// Creates a builder with a map of walls and nothing on it, for an architect to carve.
+    ///  * `width` - the width of the map
+    ///  * `height` - the height of the map
+    pub fn empty(width: i32, height: i32) -> Self {
+        let mut mb = Self {
+            map: Map::new(width, height),
+            rooms: Vec::new(),
+            monster_spawns: Vec::new(),
+            regions: Regions::default(),
+            entity_spawns: Vec::new(),
+            placed_vaults: Vec::new(),
+            player_start: Point::zero(),
+            amulet_start: Point::zero(),
+            theme: DungeonTheme::new(),
+            snapshots: None,
+        };
+        mb.fill(TileType::Wall);
         mb
     }
 
+    /// Records the map as it is now, if the level is being recorded. A phase
+    /// that left the map as it was adds nothing.
+    /// * `&mut self` - allows access to change the current `MapBuilder` instance
+    /// * `label` - what the phase did
+    pub fn snapshot<S: ToString>(&mut self, label: S) {
+        if let Some(snapshots) = self.snapshots.as_mut() {
+            if snapshots
+                .last()
+                .is_none_or(|last| last.map.tiles != self.map.tiles)
+            {
+                snapshots.push(MapSnapshot {
+                    label: label.to_string(),
+                    map: self.map.clone(),
+                });
+            }
+        }
+    }
+
     /// Fills the map with the tile specified.
     /// * `&mut self` - allows access to change the current `MapBuilder` instance
     /// * `tile` - the desired `TileType` to fill the map with
@@ -69,14 +337,51 @@ impl MapBuilder {
         self.map.tiles.iter_mut().for_each(|t| *t = tile);
     }
 
+    /// Turns the edge of the map back into walls, so nothing can walk off it.
+    /// * `&mut self` - allows access to change the current `MapBuilder` instance
+    fn wall_edges(&mut self) {
+        let (width, height) = (self.map.width, self.map.height);
+        for y in 0..height {
+            for x in 0..width {
+                if x == 0 || y == 0 || x == width - 1 || y == height - 1 {
+                    let idx = self.map.map_idx(x, y);
+                    self.map.tiles[idx] = TileType::Wall;
+                }
+            }
+        }
+    }
+
+    /// Groups the floor tiles into areas joined up by floor or doors, as tile indexes.
+    fn floor_areas(&self) -> Vec<Vec<usize>> {
+        let mut area_of = vec![usize::MAX; self.map.tiles.len()];
+        let mut areas: Vec<Vec<usize>> = Vec::new();
+        for first in 0..self.map.tiles.len() {
+            if !self.map.tiles[first].is_walkable() || area_of[first] != usize::MAX {
+                continue;
+            }
+            // flood fill the area this tile belongs to
+            let mut area = vec![first];
+            area_of[first] = areas.len();
+            let mut next = 0;
+            while next < area.len() {
+                let pt = self.map.index_to_point2d(area[next]);
+                next += 1;
+                for (dx, dy) in [(0, -1), (1, 0), (0, 1), (-1, 0)] {
+                    if let Some(idx) = self.map.try_idx(Point::new(pt.x + dx, pt.y + dy)) {
+                        if self.map.tiles[idx].is_walkable() && area_of[idx] == usize::MAX {
+                            area_of[idx] = areas.len();
+                            area.push(idx);
+                        }
+                    }
+                }
+            }
+            areas.push(area);
+        }
+        areas
+    }
+
     fn find_most_distant(&self) -> Point {
-        let dijkstra_map = DijkstraMap::new(
-            SCREEN_WIDTH,
-            SCREEN_HEIGHT,
-            &vec![self.map.point2d_to_index(self.player_start)],
-            &self.map,
-            1024.0,
-        );
+        let dijkstra_map = prefab::distances_from_start(self);
 
         const UNREACHABLE: &f32 = &f32::MAX;
         self.map.index_to_point2d(
@@ -84,7 +389,9 @@ impl MapBuilder {
                 .map
                 .iter()
                 .enumerate()
-                .filter(|(_, dist)| *dist < UNREACHABLE)
+                .filter(|(idx, dist)| {
+                    *dist < UNREACHABLE && self.map.tiles[*idx] == TileType::Floor
+                })
                 .max_by(|a, b| a.1.partial_cmp(b.1).unwrap())
                 .unwrap()
                 .0,
@@ -95,12 +402,20 @@ impl MapBuilder {
     /// * `&mut self` - allows access to change the current `MapBuilder` instance
     /// * `rng` - allows access to the RandomNumberGenerator from `bracket_random`
     fn build_random_rooms(&mut self, rng: &mut RandomNumberGenerator) {
-        // keep generating rooms until there are NUM_ROOMS rooms on the map
-        while self.rooms.len() < NUM_ROOMS {
+        let (width, height) = (self.map.width, self.map.height);
+        let num_rooms = usize::max(
+            2,
+            NUM_ROOMS * (width * height) as usize / (SCREEN_WIDTH * SCREEN_HEIGHT) as usize,
+        );
+        // keep generating rooms until there are num_rooms rooms on the map, or
+        // until it's clear they won't fit
+        let mut attempts = 0;
+        while self.rooms.len() < num_rooms && attempts < ROOM_ATTEMPTS {
+            attempts += 1;
             // generate randomly positioned rooms of random sizes
             let room = Rect::with_size(
-                rng.range(1, SCREEN_WIDTH - 10),
-                rng.range(1, SCREEN_HEIGHT - 10),
+                rng.range(1, width - 10),
+                rng.range(1, height - 10),
                 rng.range(2, 10),
                 rng.range(2, 10),
             );
@@ -116,13 +431,14 @@ impl MapBuilder {
             // the map boundaries and set their contents to floors
             if !overlap {
                 room.for_each(|p| {
-                    if p.x > 0 && p.x < SCREEN_WIDTH && p.y > 0 && p.y < SCREEN_HEIGHT {
-                        let idx = map_idx(p.x, p.y);
+                    if p.x > 0 && p.x < width && p.y > 0 && p.y < height {
+                        let idx = self.map.map_idx(p.x, p.y);
                         self.map.tiles[idx] = TileType::Floor;
                     }
                 });
 
                 self.rooms.push(room);
+                self.snapshot(format!("room {}", self.rooms.len()));
             }
         }
     }
@@ -161,7 +477,7 @@ impl MapBuilder {
     fn build_corridors(&mut self, rng: &mut RandomNumberGenerator) {
         let mut rooms = self.rooms.clone();
         // sort rooms by their center point to make sure adjacent rooms join to one another
-        rooms.sort_by(|a, b| a.center().x.cmp(&b.center().x));
+        rooms.sort_by_key(|a| a.center().x);
         // iterate through the rooms, ignoring the first one in the iterator
         for (i, room) in rooms.iter().enumerate().skip(1) {
             // obtain the center position of the current and previous room
@@ -176,13 +492,84 @@ impl MapBuilder {
                 self.apply_vertical_tunnel(prev.y, new.y, prev.x);
                 self.apply_horizontal_tunnel(prev.x, new.x, new.y);
             }
+            self.snapshot(format!("corridor {}", i));
+        }
+    }
+
+    /// Puts a closed door wherever a corridor enters a room through a gap one
+    /// tile wide, so rooms can be shut off from the corridors.
+    /// * `&mut self` - allows access to change the current `MapBuilder` instance
+    fn add_doors(&mut self) {
+        let mut doors = Vec::new();
+        for room in self.rooms.iter() {
+            // the ring of tiles around the room, without its corners, and
+            // the direction along the wall from each of them
+            let sides = (room.x1..room.x2)
+                .flat_map(|x| [Point::new(x, room.y1 - 1), Point::new(x, room.y2)])
+                .map(|pt| (pt, Point::new(1, 0)))
+                .chain(
+                    (room.y1..room.y2)
+                        .flat_map(|y| [Point::new(room.x1 - 1, y), Point::new(room.x2, y)])
+                        .map(|pt| (pt, Point::new(0, 1))),
+                );
+            for (pt, along) in sides {
+                let is_wall = |pt: Point| {
+                    self.map
+                        .try_idx(pt)
+                        .is_none_or(|idx| self.map.tiles[idx] == TileType::Wall)
+                };
+                let gap = self.map.try_idx(pt).is_some_and(|idx| {
+                    self.map.tiles[idx] == TileType::Floor
+                        && is_wall(pt - along)
+                        && is_wall(pt + along)
+                });
+                if gap {
+                    doors.push(pt);
+                }
+            }
         }
+        doors.sort_by_key(|pt| (pt.y, pt.x));
+        doors.dedup();
+        // rooms close together would otherwise get a door each, side by side
+        let mut placed = 0;
+        for pt in doors {
+            let beside_door = [(0, -1), (1, 0), (0, 1), (-1, 0)]
+                .iter()
+                .any(|(dx, dy)| self.map.door_at(Point::new(pt.x + dx, pt.y + dy)).is_some());
+            if !beside_door {
+                let idx = self.map.point2d_to_index(pt);
+                self.map.tiles[idx] = TileType::ClosedDoor;
+                placed += 1;
+            }
+        }
+        self.snapshot(format!("doors at {} corridor ends", placed));
+    }
+
+    // The number of random spawns: 50 on a screen-sized map, scaled to the size of this one.
+    fn monster_count(&self) -> usize {
+        usize::max(
+            1,
+            50 * self.map.tiles.len() / (SCREEN_WIDTH * SCREEN_HEIGHT) as usize,
+        )
     }
 
     fn spawn_monsters(&self, start: &Point, rng: &mut RandomNumberGenerator) -> Vec<Point> {
-        const NUM_MONSTERS: usize = 50;
-        let mut spawnable_tiles: Vec<Point> = self
-            .map
+        let mut spawnable_tiles = self.spawnable_tiles(start);
+
+        let mut spawns = Vec::new();
+        for _ in 0..self.monster_count() {
+            // small maps can run out of room before every monster is placed
+            if let Some(target_index) = rng.random_slice_index(&spawnable_tiles) {
+                spawns.push(spawnable_tiles[target_index]);
+                spawnable_tiles.remove(target_index);
+            }
+        }
+        spawns
+    }
+
+    // The floor tiles far enough from the start for a monster to spawn on.
+    fn spawnable_tiles(&self, start: &Point) -> Vec<Point> {
+        self.map
             .tiles
             .iter()
             .enumerate()
@@ -192,14 +579,78 @@ impl MapBuilder {
                         > 10.0
             })
             .map(|(idx, _)| self.map.index_to_point2d(idx))
-            .collect();
+            .collect()
+    }
 
-        let mut spawns = Vec::new();
-        for _ in 0..NUM_MONSTERS {
-            let target_index = rng.random_slice_index(&spawnable_tiles).unwrap();
-            spawns.push(spawnable_tiles[target_index].clone());
-            spawnable_tiles.remove(target_index);
+    /// Drops or adds random monster spawns, as the dungeon plan asks.
+    /// * `&mut self` - allows access to change the current `MapBuilder` instance
+    /// * `rng` - allows access to the RandomNumberGenerator from `bracket_random`
+    /// * `density` - the share of the architect's spawns to keep, above 1 for more
+    fn apply_monster_density(&mut self, rng: &mut RandomNumberGenerator, density: f32) {
+        let wanted = (self.monster_spawns.len() as f32 * density).round() as usize;
+        while self.monster_spawns.len() > wanted {
+            let index = rng.random_slice_index(&self.monster_spawns).unwrap();
+            self.monster_spawns.remove(index);
+        }
+        let mut spawnable_tiles = self.spawnable_tiles(&self.player_start);
+        spawnable_tiles.retain(|pt| !self.monster_spawns.contains(pt));
+        while self.monster_spawns.len() < wanted {
+            match rng.random_slice_index(&spawnable_tiles) {
+                Some(index) => self.monster_spawns.push(spawnable_tiles.remove(index)),
+                None => break,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The seeds every architect is tried with.
+    const SEEDS: u64 = 10;
+
+    #[test]
+    fn every_architect_builds_a_connected_level() {
+        let data = GameData::load().unwrap();
+        // every architect is tried on every level, planned there or not, so
+        // one that is left out of the plan for now still can't break
+        for depth in 0..data.plan.levels.len() {
+            for architect in ARCHITECT_NAMES {
+                // a plan that only allows this architect, so every seed uses it
+                let mut data = data.clone();
+                data.plan.levels[depth].architects = vec![architect.to_string()];
+                data.plan.levels[depth].layout = None;
+                for seed in 0..SEEDS {
+                    let mut rng = RandomNumberGenerator::seeded(seed);
+                    let mb =
+                        MapBuilder::new(&mut rng, SCREEN_WIDTH, SCREEN_HEIGHT, depth as u32, &data);
+                    let context = format!("{} on level {}, seed {}", architect, depth + 1, seed);
+
+                    let distances = prefab::distances_from_start(&mb);
+                    let unreachable = mb
+                        .map
+                        .tiles
+                        .iter()
+                        .zip(distances.map.iter())
+                        .filter(|(tile, distance)| tile.is_walkable() && **distance == f32::MAX)
+                        .count();
+                    assert_eq!(unreachable, 0, "{} left tiles out of reach", context);
+
+                    let floor = mb
+                        .map
+                        .tiles
+                        .iter()
+                        .filter(|t| **t == TileType::Floor)
+                        .count();
+                    assert!(
+                        floor * 100 >= mb.map.tiles.len() * MIN_FLOOR_PERCENT,
+                        "{} left {} floor tiles",
+                        context,
+                        floor
+                    );
+                }
+            }
         }
-        spawns
     }
 }
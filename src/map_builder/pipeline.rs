@@ -0,0 +1,121 @@
+use crate::prelude::*;
+
+/// How many times a level is generated again after a step rejects it, before
+/// the last attempt is kept anyway.
+const MAX_REROLLS: usize = 20;
+
+/// A step that reworks a map after its architect has carved it, such as
+/// stamping vaults or choosing spawns. Steps are chained in a `MapPipeline`.
+pub trait MetaStep {
+    /// Changes the level being built.
+    /// * `rng` - the level's map generation stream
+    /// * `mb` - the level being built
+    fn apply(&mut self, rng: &mut RandomNumberGenerator, mb: &mut MapBuilder);
+    /// Returns false if the step left the level unusable, so the pipeline
+    /// starts again from the architect.
+    /// * `mb` - the level as the step left it
+    fn accept(&self, _mb: &MapBuilder) -> bool {
+        true
+    }
+    /// The name of the step, for debug output.
+    fn name(&self) -> &'static str;
+}
+
+/// Builds a level by running an architect and then each meta step in turn,
+/// so new kinds of level are made by combining steps rather than writing an
+/// architect that does everything.
+///
+/// ```ignore
+/// let mb = MapPipeline::new(Box::new(DrunkardsWalkArchitect {}))
+///     .with(AddRooms)
+///     .with(AddDoors)
+///     .with(PlaceStart)
+///     .with(CullUnreachable)
+///     .with(PlaceExit)
+///     .with(ChooseSpawns { density: 1.0 })
+///     .build(rng, 80, 50);
+/// ```
+pub struct MapPipeline<'a> {
+    architect: Box<dyn MapArchitect>,
+    steps: Vec<Box<dyn MetaStep + 'a>>,
+    /// True to keep a snapshot of the map after every phase, see `MapSnapshot`.
+    record: bool,
+}
+
+impl<'a> MapPipeline<'a> {
+    /// Starts a pipeline with the architect that carves the initial map.
+    /// * `architect` - the initial generator
+    pub fn new(architect: Box<dyn MapArchitect>) -> Self {
+        Self {
+            architect,
+            steps: Vec::new(),
+            record: false,
+        }
+    }
+
+    /// Adds a step to the end of the pipeline.
+    /// * `step` - the step to run after those already added
+    pub fn with<S: MetaStep + 'a>(mut self, step: S) -> Self {
+        self.steps.push(Box::new(step));
+        self
+    }
+
+    /// Keeps a snapshot of the map after every phase, including those of any
+    /// attempts that were rejected, in `MapBuilder::snapshots`.
+    pub fn recording(mut self) -> Self {
+        self.record = true;
+        self
+    }
+
+    /// Runs the architect on a map of walls, walls off the edge of whatever it
+    /// carved, then runs every step in order, starting over if a step rejects
+    /// the result. After `MAX_REROLLS` rejections the next attempt is kept,
+    /// with every step run on it.
+    /// * `rng` - the level's map generation stream
+    /// * `width` - the width of the map, at least `MIN_MAP_SIZE`
+    /// * `height` - the height of the map, at least `MIN_MAP_SIZE`
+    pub fn build(
+        &mut self,
+        rng: &mut RandomNumberGenerator,
+        width: i32,
+        height: i32,
+    ) -> MapBuilder {
+        let mut attempts = 0;
+        let mut snapshots = if self.record { Some(Vec::new()) } else { None };
+        loop {
+            attempts += 1;
+            let mut mb = MapBuilder::empty(width, height);
+            mb.snapshots = snapshots.take();
+            self.architect.build(rng, &mut mb);
+            mb.wall_edges();
+            mb.snapshot(format!("{} finished", self.architect.name()));
+            // the last attempt is kept whatever the steps think of it, so
+            // every step still runs to give it a start, an exit and spawns
+            let last_attempt = attempts > MAX_REROLLS;
+            let mut rejected_by = None;
+            for step in self.steps.iter_mut() {
+                step.apply(rng, &mut mb);
+                mb.snapshot(format!("{} finished", step.name()));
+                if rejected_by.is_none() && !step.accept(&mb) {
+                    rejected_by = Some(step.name());
+                    if !last_attempt {
+                        break;
+                    }
+                }
+            }
+            match rejected_by {
+                Some(name) if !last_attempt => {
+                    // show the rejected level once more, so the restart is clear
+                    if let Some(list) = mb.snapshots.as_mut() {
+                        list.push(MapSnapshot {
+                            label: format!("{} rejected the level, starting again", name),
+                            map: mb.map.clone(),
+                        });
+                    }
+                    snapshots = mb.snapshots.take();
+                }
+                _ => return mb,
+            }
+        }
+    }
+}
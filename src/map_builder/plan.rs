@@ -0,0 +1,251 @@
+use super::prefab::{distances_from_start, stamp};
+use crate::prelude::*;
+use std::fs;
+
+/// The file describing how each dungeon level is built.
+pub const PLAN_FILE: &str = "resources/dungeon_plan.ron";
+
+/// The directory fixed level layouts are loaded from, see `LevelPlan::layout`.
+pub const LAYOUT_DIR: &str = "resources/layouts";
+
+/// Which vaults may be stamped onto a level, on top of each vault's own rules.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub enum VaultSet {
+    /// Any vault allowed on the level.
+    #[default]
+    Any,
+    /// Only the named vaults.
+    Only(Vec<String>),
+    /// No vaults at all.
+    Disabled,
+}
+
+impl VaultSet {
+    /// Returns true if the set includes a vault.
+    /// * `name` - the name of the vault
+    pub fn allows(&self, name: &str) -> bool {
+        match self {
+            VaultSet::Any => true,
+            VaultSet::Only(names) => names.iter().any(|n| n == name),
+            VaultSet::Disabled => false,
+        }
+    }
+}
+
+/// How one dungeon level is built.
+#[derive(Clone, Debug, Deserialize)]
+pub struct LevelPlan {
+    /// The architects to choose between, see `ARCHITECT_NAMES`, or empty for
+    /// any of the random ones.
+    #[serde(default)]
+    pub architects: Vec<String>,
+    /// A layout from `LAYOUT_DIR` to use instead of an architect, e.g. `Some("Amulet Chamber")`.
+    #[serde(default)]
+    pub layout: Option<String>,
+    /// True to carve rooms and corridors on top of the architect's map, see `AddRooms`.
+    #[serde(default)]
+    pub add_rooms: bool,
+    /// The share of dead ends, in percent, the `Maze` architect opens into loops.
+    #[serde(default)]
+    pub maze_loop_percent: i32,
+    /// The vaults or layouts the `WaveFunctionCollapse` architect learns from,
+    /// or empty for every vault.
+    #[serde(default)]
+    pub wfc_samples: Vec<String>,
+    /// The themes to choose between, or empty for any of them.
+    #[serde(default)]
+    pub themes: Vec<String>,
+    /// The vaults that may be stamped onto the level.
+    #[serde(default)]
+    pub vaults: VaultSet,
+    /// Scales the number of random monster and item spawns the architect picks.
+    #[serde(default = "LevelPlan::default_density")]
+    pub monster_density: f32,
+}
+
+impl Default for LevelPlan {
+    fn default() -> Self {
+        Self {
+            architects: Vec::new(),
+            layout: None,
+            add_rooms: false,
+            maze_loop_percent: 0,
+            wfc_samples: Vec::new(),
+            themes: Vec::new(),
+            vaults: VaultSet::Any,
+            monster_density: Self::default_density(),
+        }
+    }
+}
+
+impl LevelPlan {
+    fn default_density() -> f32 {
+        1.0
+    }
+
+    /// The vaults and layouts the `WaveFunctionCollapse` architect learns from.
+    /// * `data` - the loaded vaults and layouts
+    pub fn wfc_samples(&self, data: &GameData) -> Vec<Vault> {
+        if self.wfc_samples.is_empty() {
+            return data.vaults.vaults.clone();
+        }
+        self.wfc_samples
+            .iter()
+            .filter_map(|name| {
+                data.vaults
+                    .by_name(name)
+                    .or_else(|| data.layouts.by_name(name))
+            })
+            .cloned()
+            .collect()
+    }
+
+    // Lists everything wrong with the level, checking names against the loaded data.
+    fn problems(&self, vaults: &Vaults, layouts: &Vaults, themes: &Themes) -> Vec<String> {
+        let mut problems = Vec::new();
+        self.architects
+            .iter()
+            .filter(|a| !ARCHITECT_NAMES.contains(&a.as_str()))
+            .for_each(|a| {
+                problems.push(format!(
+                    "unknown architect {}, expected one of {}",
+                    a,
+                    ARCHITECT_NAMES.join(", ")
+                ))
+            });
+        if let Some(name) = &self.layout {
+            match layouts.by_name(name) {
+                Some(layout) => problems.extend(layout_problems(layout)),
+                None => problems.push(format!("there is no layout called {}", name)),
+            }
+            if !self.architects.is_empty() {
+                problems.push("a level can't have both a layout and architects".to_string());
+            }
+        }
+        let theme_names = themes.names();
+        self.themes
+            .iter()
+            .filter(|t| !theme_names.contains(&t.as_str()))
+            .for_each(|t| problems.push(format!("there is no theme called {}", t)));
+        if let VaultSet::Only(names) = &self.vaults {
+            names
+                .iter()
+                .filter(|n| vaults.by_name(n).is_none())
+                .for_each(|n| problems.push(format!("there is no vault called {}", n)));
+        }
+        for name in self.wfc_samples.iter() {
+            match vaults.by_name(name).or_else(|| layouts.by_name(name)) {
+                Some(sample) if sample.width() < 3 || sample.height() < 3 => problems.push(
+                    format!("sample {} must be at least 3x3 to learn from", name),
+                ),
+                Some(_) => {}
+                None => problems.push(format!("there is no vault or layout called {}", name)),
+            }
+        }
+        if !(0..=100).contains(&self.maze_loop_percent) {
+            problems.push("maze_loop_percent must be from 0 to 100".to_string());
+        }
+        if !self.monster_density.is_finite() || self.monster_density < 0.0 {
+            problems.push("monster_density must be 0 or more".to_string());
+        }
+        problems
+    }
+}
+
+// Lists what stops a vault from being used as a whole level.
+fn layout_problems(layout: &Vault) -> Vec<String> {
+    let mut problems = Vec::new();
+    if layout.count(&VaultTile::Start) != 1 {
+        problems.push(format!("layout {} needs exactly one Start", layout.name));
+    }
+    if layout.count(&VaultTile::Exit) != 1 {
+        problems.push(format!("layout {} needs exactly one Exit", layout.name));
+    }
+    if problems.is_empty() {
+        // stamp the layout on its own to check the exit can be walked to
+        let mut mb = MapBuilder::empty(layout.width() + 2, layout.height() + 2);
+        let area = Rect::with_size(1, 1, layout.width(), layout.height());
+        stamp(&mut mb, layout, area, Orientation::default());
+        let distances = distances_from_start(&mb);
+        if distances.map[mb.map.point2d_to_index(mb.amulet_start)] == f32::MAX {
+            problems.push(format!(
+                "layout {} has no path from the Start to the Exit",
+                layout.name
+            ));
+        }
+    }
+    // the edge of the map is always wall, so layouts must fit inside it
+    let largest = MIN_MAP_SIZE - 2;
+    if layout.width() > largest || layout.height() > largest {
+        problems.push(format!(
+            "layout {} is larger than {}x{}, the most the smallest map can hold",
+            layout.name, largest, largest
+        ));
+    }
+    problems
+}
+
+/// How every dungeon level is built, from the first down to `FINAL_LEVEL`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct DungeonPlan {
+    /// One plan per dungeon level, starting at level 1.
+    pub levels: Vec<LevelPlan>,
+}
+
+impl DungeonPlan {
+    /// Reads and validates the plan.
+    /// * `path` - the plan file, usually `PLAN_FILE`
+    /// * `vaults` - the vaults levels may name
+    /// * `layouts` - the fixed layouts levels may name, see `LAYOUT_DIR`
+    /// * `themes` - the themes levels may name
+    pub fn load(
+        path: &str,
+        vaults: &Vaults,
+        layouts: &Vaults,
+        themes: &Themes,
+    ) -> Result<Self, DataFileError> {
+        let text = fs::read_to_string(path).map_err(|e| DataFileError::new(path, e))?;
+        let plan: DungeonPlan =
+            ron::de::from_str(&text).map_err(|e| DataFileError::from_ron(path, e))?;
+
+        let mut problems = Vec::new();
+        if plan.levels.len() != FINAL_LEVEL as usize + 1 {
+            problems.push(format!(
+                "the plan has {} levels but the dungeon has {}",
+                plan.levels.len(),
+                FINAL_LEVEL + 1
+            ));
+        }
+        plan.levels.iter().enumerate().for_each(|(i, level)| {
+            level
+                .problems(vaults, layouts, themes)
+                .iter()
+                .for_each(|p| problems.push(format!("level {}: {}", i + 1, p)));
+        });
+        // the player can only start a level from a layout
+        vaults
+            .vaults
+            .iter()
+            .filter(|v| v.count(&VaultTile::Start) > 0)
+            .for_each(|v| {
+                problems.push(format!(
+                    "vault {} has a Start, which only fixed layouts may use",
+                    v.name
+                ))
+            });
+
+        if problems.is_empty() {
+            Ok(plan)
+        } else {
+            Err(DataFileError::new(path, problems.join("; ")))
+        }
+    }
+
+    /// The plan for a dungeon level.
+    /// * `depth` - the dungeon level, starting at 0
+    pub fn level(&self, depth: u32) -> &LevelPlan {
+        // plans are checked to cover every level, but deeper levels repeat the last
+        let last = self.levels.len() - 1;
+        &self.levels[usize::min(depth as usize, last)]
+    }
+}
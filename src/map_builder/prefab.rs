@@ -1,82 +1,265 @@
 use crate::prelude::*;
 
-const FORTRESS: (&str, i32, i32) = (
-    "
-------------
----######---
----#----#---
----#-M--#---
--###----###-
---M------M--
--###----###-
----#----#---
----#----#---
----######---
-------------
-",
-    12,
-    11,
-);
-
-pub fn apply_prefab(mb: &mut MapBuilder, rng: &mut RandomNumberGenerator) {
-    let mut placement = None;
-
-    let dijkstra_map = DijkstraMap::new(
-        SCREEN_WIDTH,
-        SCREEN_HEIGHT,
-        &vec![mb.map.point2d_to_index(mb.player_start)],
+/// The share of a map's tiles, in percent, that vaults may cover.
+const VAULT_BUDGET_PERCENT: i32 = 10;
+/// The number of random spots tried for each vault before giving up on it.
+const PLACEMENT_ATTEMPTS: i32 = 10;
+
+/// A vault that was stamped onto a level, for debug output.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlacedVault {
+    pub name: String,
+    /// The footprint of the vault on the map.
+    pub area: Rect,
+    pub orientation: Orientation,
+}
+
+/// Stamps the vaults allowed on this level onto the map, turned and flipped
+/// at random, until the level's vault budget is spent. Vaults never overlap.
+/// * `mb` - the level being built
+/// * `rng` - the level's map generation stream
+/// * `vaults` - every vault loaded from `VAULT_DIR`
+/// * `depth` - the dungeon level, starting at 0
+/// * `architect` - the name of the architect that built the level
+/// * `vault_set` - the vaults the dungeon plan allows on this level
+pub fn apply_prefab(
+    mb: &mut MapBuilder,
+    rng: &mut RandomNumberGenerator,
+    vaults: &Vaults,
+    depth: u32,
+    architect: &str,
+    vault_set: &VaultSet,
+) {
+    // each vault is rolled against its rarity, then the winners are tried in a random order
+    let mut candidates: Vec<&Vault> = vaults
+        .vaults
+        .iter()
+        .filter(|v| v.allowed(depth, architect) && vault_set.allows(&v.name))
+        .filter(|v| rng.range(0, v.rarity) == 0)
+        .collect();
+    let mut budget = mb.map.width * mb.map.height * VAULT_BUDGET_PERCENT / 100;
+
+    while let Some(index) = rng.random_slice_index(&candidates) {
+        let vault = candidates.remove(index);
+        let orientation = vault.random_orientation(rng);
+        let (width, height) = vault.oriented_size(orientation);
+        // vaults stay off the edge of the map, which is always wall
+        if width * height > budget || width > mb.map.width - 2 || height > mb.map.height - 2 {
+            continue;
+        }
+
+        // distances are measured again for each vault, as the last one may have changed them
+        let dijkstra_map = distances_from_start(mb);
+        let mut attempts = 0;
+        while attempts < PLACEMENT_ATTEMPTS {
+            attempts += 1;
+            let area = Rect::with_size(
+                rng.range(1, mb.map.width - width),
+                rng.range(1, mb.map.height - height),
+                width,
+                height,
+            );
+            if !footprint_is_valid(mb, &dijkstra_map, area) {
+                continue;
+            }
+
+            // everything that could be reached before must still be reachable afterwards
+            let must_reach: Vec<Point> = std::iter::once(mb.amulet_start)
+                .chain(mb.monster_spawns.iter().copied())
+                .chain(mb.entity_spawns.iter().map(|(pt, _)| *pt))
+                .filter(|pt| !area.point_in_rect(*pt) && is_reachable(mb, &dijkstra_map, *pt))
+                .collect();
+            let before = (
+                mb.map.tiles.clone(),
+                mb.monster_spawns.clone(),
+                mb.entity_spawns.clone(),
+                mb.amulet_start,
+                mb.player_start,
+            );
+            stamp(mb, vault, area, orientation);
+
+            // as must every tile of the vault that can be walked on, so no
+            // part of it is left sealed in the rock
+            let added: Vec<Point> = area
+                .point_set()
+                .into_iter()
+                .filter(|pt| mb.map.tiles[mb.map.point2d_to_index(*pt)].is_walkable())
+                .collect();
+            let stamped_dijkstra = distances_from_start(mb);
+            let connected = must_reach
+                .iter()
+                .chain(added.iter())
+                .all(|pt| is_reachable(mb, &stamped_dijkstra, *pt));
+            if !connected {
+                // roll the stamp back and try somewhere else
+                mb.map.tiles = before.0;
+                mb.monster_spawns = before.1;
+                mb.entity_spawns = before.2;
+                mb.amulet_start = before.3;
+                mb.player_start = before.4;
+                continue;
+            }
+
+            budget -= width * height;
+            mb.placed_vaults.push(PlacedVault {
+                name: vault.name.clone(),
+                area,
+                orientation,
+            });
+            mb.snapshot(format!("vault {}, {}", vault.name, orientation));
+            break;
+        }
+    }
+}
+
+/// The walking distance from the player start to every tile of the map.
+/// * `mb` - the level being built
+pub(super) fn distances_from_start(mb: &MapBuilder) -> DijkstraMap {
+    DijkstraMap::new(
+        mb.map.width,
+        mb.map.height,
+        &[mb.map.point2d_to_index(mb.player_start)],
         &mb.map,
-        1024.0,
-    );
-
-    let mut attempts = 0;
-    while placement.is_none() && attempts < 10 {
-        let dimensions = Rect::with_size(
-            rng.range(0, SCREEN_WIDTH - FORTRESS.1),
-            rng.range(0, SCREEN_HEIGHT - FORTRESS.2),
-            FORTRESS.1,
-            FORTRESS.2,
-        );
-
-        let mut can_place = false;
-        dimensions.for_each(|pt| {
+        mb.map.tiles.len() as f32,
+    )
+}
+
+fn is_reachable(mb: &MapBuilder, dijkstra_map: &DijkstraMap, pt: Point) -> bool {
+    dijkstra_map.map[mb.map.point2d_to_index(pt)] < f32::MAX
+}
+
+// A vault may only be stamped where it keeps well away from the player start,
+// covers neither the start nor the amulet, overlaps no other vault and joins
+// up with the part of the map the player can reach. It may cover rock, but no
+// floor the player can't reach.
+fn footprint_is_valid(mb: &MapBuilder, dijkstra_map: &DijkstraMap, area: Rect) -> bool {
+    let mut touches_reachable = false;
+    for pt in area.point_set() {
+        if pt == mb.player_start || pt == mb.amulet_start {
+            return false;
+        }
+        let idx = mb.map.point2d_to_index(pt);
+        let distance = dijkstra_map.map[idx];
+        if distance == f32::MAX && mb.map.tiles[idx].is_walkable() {
+            return false;
+        }
+        if distance < f32::MAX {
+            if distance <= 20.0 {
+                return false;
+            }
+            touches_reachable = true;
+        }
+    }
+    // touching footprints count as overlapping, leaving a gap between vaults
+    touches_reachable
+        && !mb
+            .placed_vaults
+            .iter()
+            .any(|placed| placed.area.intersect(&area))
+}
+
+/// Replaces the tiles and spawns inside an area with those of a vault.
+/// * `mb` - the level being built
+/// * `vault` - the vault or layout to stamp
+/// * `area` - where to stamp it, the size of the oriented vault
+/// * `orientation` - how the vault is turned and flipped
+pub(super) fn stamp(mb: &mut MapBuilder, vault: &Vault, area: Rect, orientation: Orientation) {
+    let points = area.point_set();
+    mb.monster_spawns.retain(|pt| !points.contains(pt));
+    mb.entity_spawns.retain(|(pt, _)| !points.contains(pt));
+
+    for y in 0..area.height() {
+        for x in 0..area.width() {
+            let pt = Point::new(area.x1 + x, area.y1 + y);
             let idx = mb.map.point2d_to_index(pt);
-            let distance = dijkstra_map.map[idx];
-            if distance < 2000.0 && distance > 20.0 && mb.amulet_start != pt {
-                can_place = true;
+            // vaults are validated when loaded, so every character has a tile
+            let tile = vault
+                .oriented_tile_at(x, y, orientation)
+                .unwrap_or(VaultTile::Floor);
+            mb.map.tiles[idx] = match tile {
+                VaultTile::Wall => TileType::Wall,
+                VaultTile::Door => TileType::ClosedDoor,
+                _ => TileType::Floor,
+            };
+            match tile {
+                VaultTile::Spawn => mb.monster_spawns.push(pt),
+                VaultTile::Template(name) => mb.entity_spawns.push((pt, name)),
+                VaultTile::Exit => mb.amulet_start = pt,
+                VaultTile::Start => mb.player_start = pt,
+                VaultTile::Floor | VaultTile::Wall | VaultTile::Door => {}
             }
-        });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        if can_place {
-            placement = Some(Point::new(dimensions.x1, dimensions.y1));
-            let points = dimensions.point_set();
-            mb.monster_spawns.retain(|pt| !points.contains(pt));
+    // A level that is a single corridor, with the start at the west end and
+    // the exit at the east end.
+    fn corridor_level() -> MapBuilder {
+        let mut mb = MapBuilder::empty(40, 11);
+        for x in 1..39 {
+            let idx = mb.map.map_idx(x, 5);
+            mb.map.tiles[idx] = TileType::Floor;
         }
-        attempts += 1;
+        mb.player_start = Point::new(1, 5);
+        mb.amulet_start = Point::new(38, 5);
+        mb
     }
 
-    if let Some(placement) = placement {
-        let string_vec: Vec<char> = FORTRESS
-            .0
-            .chars()
-            .filter(|a| *a != '\r' && *a != '\n')
-            .collect();
-        let mut i = 0;
-        for ty in placement.y..placement.y + FORTRESS.2 {
-            for tx in placement.x..placement.x + FORTRESS.1 {
-                let idx = map_idx(tx, ty);
-                let c = string_vec[i];
-                match c {
-                    'M' => {
-                        mb.map.tiles[idx] = TileType::Floor;
-                        mb.monster_spawns.push(Point::new(tx, ty));
-                    }
-                    '-' => mb.map.tiles[idx] = TileType::Floor,
-                    '#' => mb.map.tiles[idx] = TileType::Wall,
-                    _ => println!("No idea what to do with [{}]", c),
-                }
-                i += 1;
-            }
+    // A 3x3 vault made of a single tile.
+    fn solid_vault(name: &str, row: &str) -> Vaults {
+        Vaults {
+            vaults: vec![Vault {
+                name: name.to_string(),
+                min_depth: 0,
+                rarity: 1,
+                architects: Vec::new(),
+                legend: Default::default(),
+                rotate: false,
+                mirror: false,
+                layout: vec![row.to_string(); 3],
+            }],
         }
     }
+
+    #[test]
+    fn stamp_that_cuts_off_the_exit_is_rolled_back() {
+        let vaults = solid_vault("Plug", "###");
+        for seed in 0..50 {
+            let mut mb = corridor_level();
+            let tiles = mb.map.tiles.clone();
+            let mut rng = RandomNumberGenerator::seeded(seed);
+            apply_prefab(&mut mb, &mut rng, &vaults, 0, "Rooms", &VaultSet::Any);
+            assert!(mb.placed_vaults.is_empty(), "seed {}", seed);
+            assert!(mb.map.tiles == tiles, "seed {}", seed);
+            assert_eq!(mb.player_start, Point::new(1, 5), "seed {}", seed);
+            assert!(
+                is_reachable(&mb, &distances_from_start(&mb), mb.amulet_start),
+                "seed {}",
+                seed
+            );
+        }
+    }
+
+    #[test]
+    fn stamp_that_keeps_the_exit_reachable_is_kept() {
+        let vaults = solid_vault("Clearing", "---");
+        let placed = (0..50)
+            .filter(|seed| {
+                let mut mb = corridor_level();
+                let mut rng = RandomNumberGenerator::seeded(*seed);
+                apply_prefab(&mut mb, &mut rng, &vaults, 0, "Rooms", &VaultSet::Any);
+                assert!(is_reachable(
+                    &mb,
+                    &distances_from_start(&mb),
+                    mb.amulet_start
+                ));
+                !mb.placed_vaults.is_empty()
+            })
+            .count();
+        assert!(placed > 0);
+    }
 }
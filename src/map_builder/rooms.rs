@@ -4,25 +4,14 @@ use crate::prelude::*;
 pub struct RoomsArchitect {}
 
 impl MapArchitect for RoomsArchitect {
-    fn new(&mut self, rng: &mut RandomNumberGenerator) -> MapBuilder {
-        let mut mb = MapBuilder {
-            map: Map::new(),
-            rooms: Vec::new(),
-            monster_spawns: Vec::new(),
-            player_start: Point::zero(),
-            amulet_start: Point::zero(),
-            theme: super::themes::DungeonTheme::new(),
-        };
-
-        mb.fill(TileType::Wall);
+    fn build(&mut self, rng: &mut RandomNumberGenerator, mb: &mut MapBuilder) {
         mb.build_random_rooms(rng);
         mb.build_corridors(rng);
+        // the spawn step puts a monster in each of the other rooms
         mb.player_start = mb.rooms[0].center();
-        mb.amulet_start = mb.find_most_distant();
-        for room in mb.rooms.iter().skip(1) {
-            mb.monster_spawns.push(room.center());
-        }
+    }
 
-        mb
+    fn name(&self) -> &'static str {
+        "Rooms"
     }
 }
@@ -0,0 +1,211 @@
+use super::prefab::distances_from_start;
+use crate::prelude::*;
+
+/// Carves random rooms and corridors on top of the map, joined to what was
+/// already there, e.g. to add rooms to a drunkard's walk.
+pub struct AddRooms;
+
+impl MetaStep for AddRooms {
+    fn apply(&mut self, rng: &mut RandomNumberGenerator, mb: &mut MapBuilder) {
+        let existing_floor: Vec<Point> = (0..mb.map.tiles.len())
+            .filter(|idx| mb.map.tiles[*idx] == TileType::Floor)
+            .map(|idx| mb.map.index_to_point2d(idx))
+            .collect();
+        let first_room = mb.rooms.len();
+        mb.build_random_rooms(rng);
+        // maps that already have their share of rooms get no more
+        if mb.rooms.len() == first_room {
+            return;
+        }
+        mb.build_corridors(rng);
+
+        // dig from the first new room to the nearest floor the architect made
+        let from = mb.rooms[first_room].center();
+        let nearest = existing_floor.iter().min_by(|a, b| {
+            let distance = |pt: &Point| DistanceAlg::Pythagoras.distance2d(from, *pt);
+            distance(a).partial_cmp(&distance(b)).unwrap()
+        });
+        if let Some(to) = nearest {
+            mb.apply_horizontal_tunnel(from.x, to.x, from.y);
+            mb.apply_vertical_tunnel(from.y, to.y, to.x);
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "AddRooms"
+    }
+}
+
+/// Puts closed doors where corridors enter the level's rooms, so levels made
+/// of rooms, or given rooms by `AddRooms`, can shut them off. Levels without
+/// rooms are left as they are.
+pub struct AddDoors;
+
+impl MetaStep for AddDoors {
+    fn apply(&mut self, _rng: &mut RandomNumberGenerator, mb: &mut MapBuilder) {
+        mb.add_doors();
+    }
+
+    fn name(&self) -> &'static str {
+        "AddDoors"
+    }
+}
+
+/// Keeps the start the architect chose, or picks the tile closest to the
+/// middle of the map in the largest area of floor if it didn't choose one,
+/// rather than a sealed pocket that happens to be central.
+pub struct PlaceStart;
+
+impl MetaStep for PlaceStart {
+    fn apply(&mut self, _rng: &mut RandomNumberGenerator, mb: &mut MapBuilder) {
+        let start_idx = mb.map.point2d_to_index(mb.player_start);
+        if mb.map.tiles[start_idx] == TileType::Floor {
+            return;
+        }
+        let largest = mb
+            .floor_areas()
+            .into_iter()
+            .max_by_key(|area| area.len())
+            .expect("The architect left no floor to start on");
+        let center = Point::new(mb.map.width / 2, mb.map.height / 2);
+        let distance =
+            |idx: &usize| DistanceAlg::Pythagoras.distance2d(center, mb.map.index_to_point2d(*idx));
+        // the area may include doors, which are no place to start
+        let closest = largest
+            .iter()
+            .filter(|idx| mb.map.tiles[**idx] == TileType::Floor)
+            .min_by(|a, b| distance(a).partial_cmp(&distance(b)).unwrap())
+            .unwrap();
+        mb.player_start = mb.map.index_to_point2d(*closest);
+    }
+
+    fn name(&self) -> &'static str {
+        "PlaceStart"
+    }
+}
+
+/// Turns every floor tile the player can't walk to from the start into wall,
+/// and rejects the level if less than `MIN_FLOOR_PERCENT` of the map is left.
+pub struct CullUnreachable;
+
+impl MetaStep for CullUnreachable {
+    fn apply(&mut self, _rng: &mut RandomNumberGenerator, mb: &mut MapBuilder) {
+        let dijkstra_map = distances_from_start(mb);
+        dijkstra_map
+            .map
+            .iter()
+            .enumerate()
+            .filter(|(_, distance)| **distance == f32::MAX)
+            .for_each(|(idx, _)| mb.map.tiles[idx] = TileType::Wall);
+    }
+
+    fn accept(&self, mb: &MapBuilder) -> bool {
+        let floor = mb
+            .map
+            .tiles
+            .iter()
+            .filter(|t| **t == TileType::Floor)
+            .count();
+        floor * 100 >= mb.map.tiles.len() * MIN_FLOOR_PERCENT
+    }
+
+    fn name(&self) -> &'static str {
+        "CullUnreachable"
+    }
+}
+
+/// Puts the exit, or the Amulet of Yala, as far from the start as the player can walk.
+pub struct PlaceExit;
+
+impl MetaStep for PlaceExit {
+    fn apply(&mut self, _rng: &mut RandomNumberGenerator, mb: &mut MapBuilder) {
+        mb.amulet_start = mb.find_most_distant();
+    }
+
+    fn name(&self) -> &'static str {
+        "PlaceExit"
+    }
+}
+
+/// Chooses where random monsters and items spawn, unless the architect already
+/// has: the middle of every room but the first, or scattered floor tiles on
+/// maps without rooms.
+pub struct ChooseSpawns {
+    /// Scales the number of spawns, see `LevelPlan::monster_density`.
+    pub density: f32,
+}
+
+impl MetaStep for ChooseSpawns {
+    fn apply(&mut self, rng: &mut RandomNumberGenerator, mb: &mut MapBuilder) {
+        mb.monster_spawns = if !mb.monster_spawns.is_empty() {
+            std::mem::take(&mut mb.monster_spawns)
+        } else if mb.rooms.is_empty() {
+            mb.spawn_monsters(&mb.player_start, rng)
+        } else {
+            mb.rooms
+                .iter()
+                .filter(|room| !room.point_in_rect(mb.player_start))
+                .map(|room| room.center())
+                .collect()
+        };
+        // culling may have walled over a spawn the architect chose
+        mb.monster_spawns
+            .retain(|pt| mb.map.tiles[mb.map.point2d_to_index(*pt)] == TileType::Floor);
+        mb.apply_monster_density(rng, self.density);
+    }
+
+    fn name(&self) -> &'static str {
+        "ChooseSpawns"
+    }
+}
+
+/// Stamps vaults onto the map, see `apply_prefab`.
+pub struct Prefabs<'a> {
+    pub vaults: &'a Vaults,
+    /// The dungeon level, starting at 0.
+    pub depth: u32,
+    /// The name of the architect that carved the map.
+    pub architect: &'static str,
+    pub vault_set: &'a VaultSet,
+}
+
+impl MetaStep for Prefabs<'_> {
+    fn apply(&mut self, rng: &mut RandomNumberGenerator, mb: &mut MapBuilder) {
+        apply_prefab(
+            mb,
+            rng,
+            self.vaults,
+            self.depth,
+            self.architect,
+            self.vault_set,
+        );
+    }
+
+    fn name(&self) -> &'static str {
+        "Prefabs"
+    }
+}
+
+/// Draws the level in one of the named themes, chosen at random.
+pub struct ApplyTheme<'a> {
+    pub themes: &'a Themes,
+    /// The themes to choose between, all of them if empty.
+    pub names: &'a [String],
+}
+
+impl MetaStep for ApplyTheme<'_> {
+    fn apply(&mut self, rng: &mut RandomNumberGenerator, mb: &mut MapBuilder) {
+        let names = if self.names.is_empty() {
+            self.themes.names()
+        } else {
+            self.names.iter().map(|name| name.as_str()).collect()
+        };
+        let name = names[rng.range(0, names.len())];
+        // the dungeon plan is checked to only name themes that exist
+        mb.theme = self.themes.by_name(name).unwrap();
+    }
+
+    fn name(&self) -> &'static str {
+        "ApplyTheme"
+    }
+}
@@ -1,8 +1,14 @@
 use crate::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+
+/// The directory the game loads data-driven themes from.
+pub const THEME_DIR: &str = "resources/themes";
 
 pub struct DungeonTheme {}
 
 impl DungeonTheme {
+    #[allow(clippy::new_ret_no_self)]
     pub fn new() -> Box<dyn MapTheme> {
         Box::new(Self {})
     }
@@ -14,13 +20,20 @@ impl MapTheme for DungeonTheme {
             TileType::Floor => to_cp437('.'),
             TileType::Wall => to_cp437('#'),
             TileType::Exit => to_cp437('>'),
+            TileType::ClosedDoor => to_cp437('+'),
+            TileType::OpenDoor => to_cp437('-'),
         }
     }
+
+    fn name(&self) -> &str {
+        "Dungeon"
+    }
 }
 
 pub struct ForestTheme {}
 
 impl ForestTheme {
+    #[allow(clippy::new_ret_no_self)]
     pub fn new() -> Box<dyn MapTheme> {
         Box::new(Self {})
     }
@@ -32,6 +45,243 @@ impl MapTheme for ForestTheme {
             TileType::Floor => to_cp437(';'),
             TileType::Wall => to_cp437('"'),
             TileType::Exit => to_cp437('>'),
+            TileType::ClosedDoor => to_cp437('+'),
+            TileType::OpenDoor => to_cp437('-'),
+        }
+    }
+
+    fn name(&self) -> &str {
+        "Forest"
+    }
+}
+
+/// Looks up one of the built-in themes by the name it reports through `MapTheme::name`.
+/// * `name` - the theme name, as stored in a saved game
+pub fn theme_by_name(name: &str) -> Option<Box<dyn MapTheme>> {
+    match name {
+        "Dungeon" => Some(DungeonTheme::new()),
+        "Forest" => Some(ForestTheme::new()),
+        _ => None,
+    }
+}
+
+/// How one kind of tile is drawn, as written in a theme file.
+#[derive(Clone, Debug, Deserialize)]
+struct TileStyleFile {
+    /// The glyphs to choose between, picked by position.
+    glyphs: Vec<char>,
+    /// The foreground and background colors while the tile is in view, as `#rrggbb`.
+    visible: (String, String),
+    /// The colors once the tile is out of view but remembered.
+    remembered: (String, String),
+}
+
+/// A theme as written in a theme file.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename = "Theme")]
+struct ThemeFile {
+    name: String,
+    tiles: HashMap<TileType, TileStyleFile>,
+    /// Colors for entities by name, `Player` included, in place of their own.
+    #[serde(default)]
+    palette: HashMap<String, (String, String)>,
+}
+
+/// How one kind of tile is drawn by a `DataTheme`.
+#[derive(Clone, Debug)]
+struct TileStyle {
+    glyphs: Vec<FontCharType>,
+    visible: ColorPair,
+    remembered: ColorPair,
+}
+
+/// A theme loaded from a RON file in `THEME_DIR`, with glyph variants and colors.
+#[derive(Clone, Debug)]
+pub struct DataTheme {
+    name: String,
+    tiles: HashMap<TileType, TileStyle>,
+    palette: HashMap<String, ColorPair>,
+}
+
+impl DataTheme {
+    /// Reads and checks a theme file.
+    /// * `path` - the theme file
+    pub fn load(path: &str) -> Result<Self, DataFileError> {
+        let text = fs::read_to_string(path).map_err(|e| DataFileError::new(path, e))?;
+        let file: ThemeFile =
+            ron::de::from_str(&text).map_err(|e| DataFileError::from_ron(path, e))?;
+        let colors = |(fg, bg): &(String, String)| -> Result<ColorPair, DataFileError> {
+            let parse = |hex: &String| {
+                RGB::from_hex(hex).map_err(|_| {
+                    DataFileError::new(path, format!("{} is not a #rrggbb color", hex))
+                })
+            };
+            Ok(ColorPair::new(parse(fg)?, parse(bg)?))
+        };
+
+        let mut tiles = HashMap::new();
+        for (tile_type, style) in file.tiles.iter() {
+            if style.glyphs.is_empty() {
+                return Err(DataFileError::new(
+                    path,
+                    format!("{:?} needs at least one glyph", tile_type),
+                ));
+            }
+            tiles.insert(
+                *tile_type,
+                TileStyle {
+                    glyphs: style.glyphs.iter().map(|c| to_cp437(*c)).collect(),
+                    visible: colors(&style.visible)?,
+                    remembered: colors(&style.remembered)?,
+                },
+            );
         }
+        for tile_type in [TileType::Floor, TileType::Wall, TileType::Exit] {
+            if !tiles.contains_key(&tile_type) {
+                return Err(DataFileError::new(
+                    path,
+                    format!("there is no style for {:?} tiles", tile_type),
+                ));
+            }
+        }
+        // themes written before doors existed draw them in the floor's colors
+        for tile_type in [TileType::ClosedDoor, TileType::OpenDoor] {
+            if !tiles.contains_key(&tile_type) {
+                let floor = &tiles[&TileType::Floor];
+                let door = TileStyle {
+                    glyphs: vec![DungeonTheme {}.tile_to_render(tile_type)],
+                    ..floor.clone()
+                };
+                tiles.insert(tile_type, door);
+            }
+        }
+        let palette = file
+            .palette
+            .iter()
+            .map(|(name, pair)| Ok((name.clone(), colors(pair)?)))
+            .collect::<Result<HashMap<_, _>, DataFileError>>()?;
+        if theme_by_name(&file.name).is_some() {
+            return Err(DataFileError::new(
+                path,
+                format!("{} is the name of a built-in theme", file.name),
+            ));
+        }
+        Ok(Self {
+            name: file.name,
+            tiles,
+            palette,
+        })
+    }
+
+    // The style of a tile type, falling back on floors for any the file leaves out.
+    fn style(&self, tile_type: TileType) -> &TileStyle {
+        self.tiles
+            .get(&tile_type)
+            .unwrap_or_else(|| &self.tiles[&TileType::Floor])
+    }
+}
+
+impl MapTheme for DataTheme {
+    fn tile_to_render(&self, tile_type: TileType) -> FontCharType {
+        self.style(tile_type).glyphs[0]
+    }
+
+    fn tile_glyph(&self, tile_type: TileType, pt: Point) -> FontCharType {
+        let glyphs = &self.style(tile_type).glyphs;
+        // a cheap hash of the position, so each tile always shows the same variant
+        let hash = (pt.x as u32).wrapping_mul(73_856_093) ^ (pt.y as u32).wrapping_mul(19_349_663);
+        glyphs[hash as usize % glyphs.len()]
+    }
+
+    fn tile_colors(&self, tile_type: TileType, visible: bool) -> ColorPair {
+        let style = self.style(tile_type);
+        if visible {
+            style.visible
+        } else {
+            style.remembered
+        }
+    }
+
+    fn entity_colors(&self, name: &str) -> Option<ColorPair> {
+        self.palette.get(name).copied()
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Every data-driven theme, alongside the built-in `DungeonTheme` and `ForestTheme`.
+#[derive(Clone, Debug, Default)]
+pub struct Themes {
+    pub themes: Vec<DataTheme>,
+}
+
+impl Themes {
+    /// The names of the built-in themes.
+    pub const BUILT_IN: [&'static str; 2] = ["Dungeon", "Forest"];
+
+    /// Reads every `.ron` file in a directory, see `ron_files`. Every theme
+    /// must have a name of its own.
+    /// * `dir` - the theme directory, usually `THEME_DIR`
+    pub fn load(dir: &str) -> Result<Self, DataFileError> {
+        let mut loaded: Vec<(String, DataTheme)> = Vec::new();
+        for path in ron_files(dir)? {
+            let path = path.to_string_lossy().to_string();
+            let theme = DataTheme::load(&path)?;
+            if let Some((first, _)) = loaded.iter().find(|(_, t)| t.name == theme.name) {
+                return Err(DataFileError::new(
+                    &path,
+                    format!(
+                        "{} is already the name of the theme in {}",
+                        theme.name, first
+                    ),
+                ));
+            }
+            loaded.push((path, theme));
+        }
+        Ok(Self {
+            themes: loaded.into_iter().map(|(_, theme)| theme).collect(),
+        })
+    }
+
+    /// The names of every theme, built-in ones first.
+    pub fn names(&self) -> Vec<&str> {
+        Self::BUILT_IN
+            .iter()
+            .copied()
+            .chain(self.themes.iter().map(|theme| theme.name.as_str()))
+            .collect()
+    }
+
+    /// Looks up a built-in or data-driven theme by name.
+    /// * `name` - the theme name, as stored in a saved game
+    pub fn by_name(&self, name: &str) -> Option<Box<dyn MapTheme>> {
+        theme_by_name(name).or_else(|| {
+            self.themes
+                .iter()
+                .find(|theme| theme.name == name)
+                .map(|theme| Box::new(theme.clone()) as Box<dyn MapTheme>)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn two_themes_with_the_same_name_are_rejected() {
+        let dir = std::env::temp_dir().join(format!("dungeoncrawl-themes-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let crypt = fs::read_to_string(format!("{}/crypt.ron", THEME_DIR)).unwrap();
+        fs::write(dir.join("a.ron"), &crypt).unwrap();
+        fs::write(dir.join("b.ron"), &crypt).unwrap();
+
+        let result = Themes::load(&dir.to_string_lossy());
+        fs::remove_dir_all(&dir).unwrap();
+        let error = result.unwrap_err();
+        assert!(error.path.ends_with("b.ron"));
+        assert!(error.message.ends_with("a.ron"));
     }
 }
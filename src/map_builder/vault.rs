@@ -0,0 +1,385 @@
+use crate::prelude::*;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+
+/// The directory the game loads its vaults from.
+pub const VAULT_DIR: &str = "resources/vaults";
+
+/// The names vault files use for the architects, see `MapArchitect::name`.
+pub const ARCHITECT_NAMES: [&str; 8] = [
+    "Rooms",
+    "CellularAutomata",
+    "DrunkardsWalk",
+    "Empty",
+    "Bsp",
+    "Maze",
+    "Voronoi",
+    "WaveFunctionCollapse",
+];
+
+/// What a character in a vault layout places on the map.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub enum VaultTile {
+    Floor,
+    Wall,
+    /// A floor tile where a random monster or item may spawn.
+    Spawn,
+    /// A floor tile the named template is spawned on, e.g. `Template("Orc")`.
+    Template(String),
+    /// The level's exit, or the Amulet of Yala on the last level.
+    Exit,
+    /// Where the player starts, only allowed in fixed layouts, see `LAYOUT_DIR`.
+    Start,
+    /// A closed door, which the player and monsters can open.
+    Door,
+}
+
+/// How a vault is turned and flipped when it is stamped onto a map.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Orientation {
+    /// The number of quarter turns clockwise, from 0 to 3.
+    pub quarter_turns: i32,
+    /// True to flip the vault left to right after turning it.
+    pub mirrored: bool,
+}
+
+impl fmt::Display for Orientation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "turned {} degrees", self.quarter_turns * 90)?;
+        if self.mirrored {
+            write!(f, " and mirrored")?;
+        }
+        Ok(())
+    }
+}
+
+/// A hand-made map section, stamped onto generated levels.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Vault {
+    pub name: String,
+    /// The shallowest dungeon level the vault can appear on, starting at 0.
+    #[serde(default)]
+    pub min_depth: u32,
+    /// The vault appears on roughly one in `rarity` of the levels it is allowed on.
+    #[serde(default = "Vault::default_rarity")]
+    pub rarity: u32,
+    /// The architects whose maps the vault suits, or empty for all of them.
+    #[serde(default)]
+    pub architects: Vec<String>,
+    /// What each character of the layout places, on top of `Vault::default_legend`.
+    #[serde(default)]
+    pub legend: HashMap<char, VaultTile>,
+    /// True if the vault may be turned by quarter turns when it is placed.
+    #[serde(default = "Vault::default_transform")]
+    pub rotate: bool,
+    /// True if the vault may be flipped left to right when it is placed.
+    #[serde(default = "Vault::default_transform")]
+    pub mirror: bool,
+    /// The rows of the vault, all the same width.
+    pub layout: Vec<String>,
+}
+
+impl Vault {
+    fn default_rarity() -> u32 {
+        1
+    }
+
+    fn default_transform() -> bool {
+        true
+    }
+
+    /// The characters every vault understands without a legend: `#` for walls,
+    /// `-` for floors, `+` for doors and `M` for random spawns.
+    pub fn default_legend() -> HashMap<char, VaultTile> {
+        [
+            ('#', VaultTile::Wall),
+            ('-', VaultTile::Floor),
+            ('+', VaultTile::Door),
+            ('M', VaultTile::Spawn),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    /// The width of the vault, in tiles.
+    pub fn width(&self) -> i32 {
+        self.layout
+            .first()
+            .map_or(0, |row| row.chars().count() as i32)
+    }
+
+    /// The height of the vault, in tiles.
+    pub fn height(&self) -> i32 {
+        self.layout.len() as i32
+    }
+
+    /// What the vault places at a position within its layout.
+    /// * `x` - the column, starting at 0
+    /// * `y` - the row, starting at 0
+    pub fn tile_at(&self, x: i32, y: i32) -> Option<VaultTile> {
+        let c = self.layout.get(y as usize)?.chars().nth(x as usize)?;
+        self.legend
+            .get(&c)
+            .cloned()
+            .or_else(|| Self::default_legend().remove(&c))
+    }
+
+    /// The number of times a tile appears in the layout.
+    /// * `tile` - the tile to count
+    pub fn count(&self, tile: &VaultTile) -> usize {
+        (0..self.height())
+            .flat_map(|y| (0..self.width()).map(move |x| (x, y)))
+            .filter(|(x, y)| self.tile_at(*x, *y).as_ref() == Some(tile))
+            .count()
+    }
+
+    /// Picks one of the orientations the vault allows at random.
+    /// * `rng` - the level's map generation stream
+    pub fn random_orientation(&self, rng: &mut RandomNumberGenerator) -> Orientation {
+        Orientation {
+            quarter_turns: if self.rotate { rng.range(0, 4) } else { 0 },
+            mirrored: self.mirror && rng.range(0, 2) == 1,
+        }
+    }
+
+    /// The width and height of the vault once it has been oriented.
+    /// * `orientation` - how the vault is turned and flipped
+    pub fn oriented_size(&self, orientation: Orientation) -> (i32, i32) {
+        if orientation.quarter_turns % 2 == 1 {
+            (self.height(), self.width())
+        } else {
+            (self.width(), self.height())
+        }
+    }
+
+    /// What the oriented vault places at a position within its footprint.
+    /// * `x` - the column of the oriented vault, starting at 0
+    /// * `y` - the row of the oriented vault, starting at 0
+    /// * `orientation` - how the vault is turned and flipped
+    pub fn oriented_tile_at(&self, x: i32, y: i32, orientation: Orientation) -> Option<VaultTile> {
+        let (width, height) = (self.width(), self.height());
+        let (oriented_width, _) = self.oriented_size(orientation);
+        let x = if orientation.mirrored {
+            oriented_width - 1 - x
+        } else {
+            x
+        };
+        // map the turned position back onto the layout as written
+        let (source_x, source_y) = match orientation.quarter_turns.rem_euclid(4) {
+            1 => (y, height - 1 - x),
+            2 => (width - 1 - x, height - 1 - y),
+            3 => (width - 1 - y, x),
+            _ => (x, y),
+        };
+        self.tile_at(source_x, source_y)
+    }
+
+    /// Returns true if the vault may be placed on a level.
+    /// * `depth` - the dungeon level, starting at 0
+    /// * `architect` - the name of the architect that built the level
+    pub fn allowed(&self, depth: u32, architect: &str) -> bool {
+        depth >= self.min_depth
+            && (self.architects.is_empty() || self.architects.iter().any(|a| a == architect))
+    }
+
+    // Lists the templates the legend names that are not in `templates`.
+    fn missing_templates(&self, templates: &Templates) -> Vec<String> {
+        self.legend
+            .values()
+            .filter_map(|tile| match tile {
+                VaultTile::Template(name)
+                    if !templates.entities.iter().any(|t| &t.name == name) =>
+                {
+                    Some(format!("there is no template called {}", name))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    // Lists everything wrong with the vault, checking template names against `templates`.
+    fn problems(&self, templates: &Templates) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.layout.is_empty() {
+            problems.push("the layout is empty".to_string());
+        }
+        if self.rarity < 1 {
+            problems.push("rarity must be at least 1".to_string());
+        }
+        self.layout
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| row.chars().count() as i32 != self.width())
+            .for_each(|(y, _)| {
+                problems.push(format!(
+                    "row {} is not {} characters wide",
+                    y + 1,
+                    self.width()
+                ))
+            });
+        for (y, row) in self.layout.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                if self.tile_at(x as i32, y as i32).is_none() {
+                    problems.push(format!(
+                        "row {} column {}: '{}' is not in the legend",
+                        y + 1,
+                        x + 1,
+                        c
+                    ));
+                }
+            }
+        }
+        problems.extend(self.missing_templates(templates));
+        self.architects
+            .iter()
+            .filter(|a| !ARCHITECT_NAMES.contains(&a.as_str()))
+            .for_each(|a| {
+                problems.push(format!(
+                    "unknown architect {}, expected one of {}",
+                    a,
+                    ARCHITECT_NAMES.join(", ")
+                ))
+            });
+        problems
+    }
+}
+
+/// Every vault the map builders can choose from.
+#[derive(Clone, Debug, Default)]
+pub struct Vaults {
+    pub vaults: Vec<Vault>,
+}
+
+impl Vaults {
+    /// Reads every `.ron` file in a directory, see `ron_files`.
+    /// * `dir` - the vault directory, usually `VAULT_DIR`
+    /// * `templates` - the templates vault legends may name
+    pub fn load(dir: &str, templates: &Templates) -> Result<Self, DataFileError> {
+        let vaults = ron_files(dir)?
+            .iter()
+            .map(|path| Self::load_vault(&path.to_string_lossy(), templates))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { vaults })
+    }
+
+    /// Lists every vault whose legend names a template that is not in
+    /// `templates`, such as one renamed since the vaults were loaded.
+    /// * `templates` - the templates vault legends may name
+    pub fn missing_templates(&self, templates: &Templates) -> Vec<String> {
+        self.vaults
+            .iter()
+            .flat_map(|vault| {
+                vault
+                    .missing_templates(templates)
+                    .into_iter()
+                    .map(move |problem| format!("{}: {}", vault.name, problem))
+            })
+            .collect()
+    }
+
+    /// Finds a vault by name.
+    /// * `name` - the name in the vault file
+    pub fn by_name(&self, name: &str) -> Option<&Vault> {
+        self.vaults.iter().find(|v| v.name == name)
+    }
+
+    // Reads and validates a single vault file.
+    fn load_vault(path: &str, templates: &Templates) -> Result<Vault, DataFileError> {
+        let text = fs::read_to_string(path).map_err(|e| DataFileError::new(path, e))?;
+        let vault: Vault =
+            ron::de::from_str(&text).map_err(|e| DataFileError::from_ron(path, e))?;
+        let problems = vault.problems(templates);
+        if problems.is_empty() {
+            Ok(vault)
+        } else {
+            Err(DataFileError::new(path, problems.join("; ")))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A 3x2 vault whose every tile is a different template, named after its character.
+    fn lettered_vault() -> Vault {
+        Vault {
+            name: "Letters".to_string(),
+            min_depth: 0,
+            rarity: 1,
+            architects: Vec::new(),
+            legend: "abcdef"
+                .chars()
+                .map(|c| (c, VaultTile::Template(c.to_string())))
+                .collect(),
+            rotate: true,
+            mirror: true,
+            layout: vec!["abc".to_string(), "def".to_string()],
+        }
+    }
+
+    // The rows of the vault as it is stamped in an orientation.
+    fn oriented_rows(vault: &Vault, quarter_turns: i32, mirrored: bool) -> Vec<String> {
+        let orientation = Orientation {
+            quarter_turns,
+            mirrored,
+        };
+        let (width, height) = vault.oriented_size(orientation);
+        (0..height)
+            .map(|y| {
+                (0..width)
+                    .map(|x| match vault.oriented_tile_at(x, y, orientation) {
+                        Some(VaultTile::Template(name)) => name,
+                        tile => panic!("unexpected tile {:?}", tile),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn quarter_turns_rotate_clockwise() {
+        let vault = lettered_vault();
+        assert_eq!(oriented_rows(&vault, 0, false), ["abc", "def"]);
+        assert_eq!(oriented_rows(&vault, 1, false), ["da", "eb", "fc"]);
+        assert_eq!(oriented_rows(&vault, 2, false), ["fed", "cba"]);
+        assert_eq!(oriented_rows(&vault, 3, false), ["cf", "be", "ad"]);
+    }
+
+    #[test]
+    fn mirroring_flips_after_turning() {
+        let vault = lettered_vault();
+        assert_eq!(oriented_rows(&vault, 0, true), ["cba", "fed"]);
+        assert_eq!(oriented_rows(&vault, 1, true), ["ad", "be", "cf"]);
+        assert_eq!(oriented_rows(&vault, 2, true), ["def", "abc"]);
+        assert_eq!(oriented_rows(&vault, 3, true), ["fc", "eb", "da"]);
+    }
+
+    #[test]
+    fn turned_vaults_swap_width_and_height() {
+        let vault = lettered_vault();
+        let turned = |quarter_turns| Orientation {
+            quarter_turns,
+            mirrored: false,
+        };
+        assert_eq!(vault.oriented_size(turned(0)), (3, 2));
+        assert_eq!(vault.oriented_size(turned(1)), (2, 3));
+        assert_eq!(vault.oriented_size(turned(2)), (3, 2));
+        assert_eq!(vault.oriented_size(turned(3)), (2, 3));
+    }
+
+    #[test]
+    fn legend_naming_a_removed_template_is_reported() {
+        let vaults = Vaults {
+            vaults: vec![lettered_vault()],
+        };
+        let templates = Templates {
+            entities: Vec::new(),
+        };
+        let mut missing = vaults.missing_templates(&templates);
+        missing.sort();
+        assert_eq!(missing.len(), 6);
+        assert_eq!(missing[0], "Letters: there is no template called a");
+    }
+}
@@ -0,0 +1,138 @@
+use super::MapArchitect;
+use crate::prelude::*;
+use std::collections::HashMap;
+
+/// The number of regions on a screen-sized map, scaled to the size of the map.
+const NUM_REGIONS: usize = 12;
+/// The chance, in percent, of each tile of a wall between two regions being
+/// opened up, on top of the one gap every wall gets.
+const GAP_PERCENT: i32 = 15;
+
+/// Splits the map into large irregular regions, each the tiles closest to a
+/// random seed, walled off from each other apart from a few gaps. The regions
+/// are kept in `MapBuilder::regions`, so each can spawn its own monsters.
+pub struct VoronoiArchitect {}
+
+impl MapArchitect for VoronoiArchitect {
+    fn build(&mut self, rng: &mut RandomNumberGenerator, mb: &mut MapBuilder) {
+        let (width, height) = (mb.map.width, mb.map.height);
+        let num_regions = usize::max(
+            3,
+            NUM_REGIONS * mb.map.tiles.len() / (SCREEN_WIDTH * SCREEN_HEIGHT) as usize,
+        );
+        let seeds: Vec<Point> = (0..num_regions)
+            .map(|_| Point::new(rng.range(1, width - 1), rng.range(1, height - 1)))
+            .collect();
+        // irregular metrics give ragged borders, Pythagoras gives smooth ones
+        let metric = match rng.range(0, 3) {
+            0 => DistanceAlg::Pythagoras,
+            1 => DistanceAlg::Manhattan,
+            _ => DistanceAlg::Chebyshev,
+        };
+        let membership: Vec<usize> = (0..mb.map.tiles.len())
+            .map(|idx| {
+                let pt = mb.map.index_to_point2d(idx);
+                (0..seeds.len())
+                    .min_by(|a, b| {
+                        let distance = |i: &usize| metric.distance2d(seeds[*i], pt);
+                        distance(a).partial_cmp(&distance(b)).unwrap()
+                    })
+                    .unwrap()
+            })
+            .collect();
+
+        // every tile bordering a different region to its right or below becomes wall
+        for y in 1..height - 1 {
+            for x in 1..width - 1 {
+                let idx = mb.map.map_idx(x, y);
+                let region = membership[idx];
+                let border = membership[mb.map.map_idx(x + 1, y)] != region
+                    || membership[mb.map.map_idx(x, y + 1)] != region;
+                if !border {
+                    mb.map.tiles[idx] = TileType::Floor;
+                }
+            }
+        }
+        mb.snapshot(format!("walled off {} regions", num_regions));
+        self.open_gaps(rng, mb, &membership);
+        mb.snapshot("opened gaps between the regions");
+
+        // regions walled over entirely are dropped, and the rest numbered in order
+        let mut has_floor = vec![false; num_regions];
+        (0..mb.map.tiles.len())
+            .filter(|idx| mb.map.tiles[*idx] == TileType::Floor)
+            .for_each(|idx| has_floor[membership[idx]] = true);
+        let mut next_id = 0;
+        let ids: Vec<Option<usize>> = has_floor
+            .iter()
+            .map(|has_floor| {
+                has_floor.then(|| {
+                    next_id += 1;
+                    next_id - 1
+                })
+            })
+            .collect();
+        mb.regions = Regions::new(
+            width,
+            membership.iter().map(|region| ids[*region]).collect(),
+        );
+    }
+
+    fn name(&self) -> &'static str {
+        "Voronoi"
+    }
+}
+
+impl VoronoiArchitect {
+    /// Opens at least one gap in the wall between every pair of neighbouring
+    /// regions, and a few more at random.
+    /// * `rng` - the level's map generation stream
+    /// * `mb` - the level being built
+    /// * `membership` - the region of every tile
+    fn open_gaps(
+        &self,
+        rng: &mut RandomNumberGenerator,
+        mb: &mut MapBuilder,
+        membership: &[usize],
+    ) {
+        // the wall tiles that would join two regions, by pair of regions
+        let mut walls: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+        let floor_region = |pt: Point| {
+            mb.map
+                .try_idx(pt)
+                .filter(|idx| mb.map.tiles[*idx] == TileType::Floor)
+                .map(|idx| membership[idx])
+        };
+        for y in 1..mb.map.height - 1 {
+            for x in 1..mb.map.width - 1 {
+                let idx = mb.map.map_idx(x, y);
+                if mb.map.tiles[idx] != TileType::Wall {
+                    continue;
+                }
+                let sides = [
+                    (Point::new(x - 1, y), Point::new(x + 1, y)),
+                    (Point::new(x, y - 1), Point::new(x, y + 1)),
+                ];
+                for (a, b) in sides {
+                    if let (Some(a), Some(b)) = (floor_region(a), floor_region(b)) {
+                        if a != b {
+                            walls.entry((a.min(b), a.max(b))).or_default().push(idx);
+                        }
+                    }
+                }
+            }
+        }
+
+        // visit the pairs in a fixed order, so seeds give the same map every time
+        let mut pairs: Vec<_> = walls.into_iter().collect();
+        pairs.sort();
+        for (_, candidates) in pairs {
+            let gap = candidates[rng.range(0, candidates.len())];
+            mb.map.tiles[gap] = TileType::Floor;
+            candidates
+                .iter()
+                .filter(|_| rng.range(0, 100) < GAP_PERCENT)
+                .for_each(|idx| mb.map.tiles[*idx] = TileType::Floor);
+        }
+    }
+}
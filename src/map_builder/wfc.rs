@@ -0,0 +1,316 @@
+use super::MapArchitect;
+use crate::prelude::*;
+use std::collections::{HashMap, VecDeque};
+
+/// The width and height of the patterns learned from the samples.
+const PATTERN_SIZE: i32 = 3;
+/// How many decisions back a contradiction can be undone before starting over.
+const MAX_SNAPSHOTS: usize = 64;
+/// How many times the map is started over before giving up on the samples.
+const MAX_ATTEMPTS: usize = 10;
+/// The share of the map, in percent, that must end up as joined-up floor.
+const MIN_JOINED_PERCENT: usize = 25;
+
+/// Learns which patterns of wall and floor sit next to each other in the
+/// samples, such as vaults, and fills the map with the same patterns using
+/// wave function collapse, so levels share their hand-made look.
+pub struct WaveFunctionCollapseArchitect {
+    /// The vaults and layouts to learn from, all orientations included.
+    pub samples: Vec<Vault>,
+}
+
+impl MapArchitect for WaveFunctionCollapseArchitect {
+    fn build(&mut self, rng: &mut RandomNumberGenerator, mb: &mut MapBuilder) {
+        let patterns = Patterns::learn(&self.samples);
+        // the edge of the map stays wall, so only the inside is generated
+        let (width, height) = (mb.map.width - 2, mb.map.height - 2);
+        for attempt in 1..=MAX_ATTEMPTS {
+            let floor = match patterns.collapse(rng, width, height) {
+                Some(floor) => floor,
+                None => continue,
+            };
+            for y in 0..height {
+                for x in 0..width {
+                    let idx = mb.map.map_idx(x + 1, y + 1);
+                    mb.map.tiles[idx] = if floor[(y * width + x) as usize] {
+                        TileType::Floor
+                    } else {
+                        TileType::Wall
+                    };
+                }
+            }
+            mb.snapshot(format!("attempt {}", attempt));
+            // the start goes in the largest area, and the rest is culled
+            let largest = mb.floor_areas().iter().map(|area| area.len()).max();
+            if largest.unwrap_or(0) * 100 >= mb.map.tiles.len() * MIN_JOINED_PERCENT {
+                return;
+            }
+        }
+        // the samples can't make a usable level, so fall back to caves
+        mb.fill(TileType::Wall);
+        mb.snapshot("the samples made no usable level, falling back on caves");
+        CellularAutomataArchitect {}.build(rng, mb);
+    }
+
+    fn name(&self) -> &'static str {
+        "WaveFunctionCollapse"
+    }
+}
+
+/// The patterns found in the samples, how often each appears and which may
+/// overlap which.
+struct Patterns {
+    /// Each pattern's tiles, row by row, true for floor.
+    tiles: Vec<Vec<bool>>,
+    weights: Vec<i32>,
+    /// For each direction (up, right, down, left) and pattern, the patterns that
+    /// may sit next to it that way, as a bit set.
+    compatible: [Vec<Vec<u64>>; 4],
+    /// The number of `u64`s in a bit set of patterns.
+    words: usize,
+}
+
+/// The offset of the neighbouring cell in each direction: up, right, down, left.
+const DIRECTIONS: [(i32, i32); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+
+impl Patterns {
+    /// Collects every pattern in every orientation of the samples.
+    /// * `samples` - the vaults to learn from, see `DungeonPlan`
+    fn learn(samples: &[Vault]) -> Self {
+        let mut index: HashMap<Vec<bool>, usize> = HashMap::new();
+        let mut tiles = Vec::new();
+        let mut weights = Vec::new();
+        for sample in samples {
+            for quarter_turns in 0..4 {
+                for mirrored in [false, true] {
+                    let orientation = Orientation {
+                        quarter_turns,
+                        mirrored,
+                    };
+                    let (width, height) = sample.oriented_size(orientation);
+                    for y in 0..=height - PATTERN_SIZE {
+                        for x in 0..=width - PATTERN_SIZE {
+                            let pattern: Vec<bool> = (0..PATTERN_SIZE * PATTERN_SIZE)
+                                .map(|i| {
+                                    sample.oriented_tile_at(
+                                        x + i % PATTERN_SIZE,
+                                        y + i / PATTERN_SIZE,
+                                        orientation,
+                                    ) != Some(VaultTile::Wall)
+                                })
+                                .collect();
+                            let i = *index.entry(pattern.clone()).or_insert_with(|| {
+                                tiles.push(pattern);
+                                weights.push(0);
+                                tiles.len() - 1
+                            });
+                            weights[i] += 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        let words = tiles.len().div_ceil(64);
+        let compatible = DIRECTIONS.map(|(dx, dy)| {
+            tiles
+                .iter()
+                .map(|a| {
+                    let mut set = vec![0u64; words];
+                    tiles
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, b)| Self::overlaps(a, b, dx, dy))
+                        .for_each(|(j, _)| set[j / 64] |= 1 << (j % 64));
+                    set
+                })
+                .collect()
+        });
+        Self {
+            tiles,
+            weights,
+            compatible,
+            words,
+        }
+    }
+
+    /// Returns true if pattern `b`, placed at an offset from pattern `a`,
+    /// agrees with `a` wherever they overlap.
+    fn overlaps(a: &[bool], b: &[bool], dx: i32, dy: i32) -> bool {
+        (0..PATTERN_SIZE).all(|y| {
+            (0..PATTERN_SIZE).all(|x| {
+                let (bx, by) = (x - dx, y - dy);
+                bx < 0
+                    || by < 0
+                    || bx >= PATTERN_SIZE
+                    || by >= PATTERN_SIZE
+                    || a[(y * PATTERN_SIZE + x) as usize] == b[(by * PATTERN_SIZE + bx) as usize]
+            })
+        })
+    }
+
+    /// Fills an area with patterns, undoing recent choices when they leave a
+    /// cell with no pattern that fits. Returns the floor tiles row by row, or
+    /// `None` if it had to give up.
+    /// * `rng` - the level's map generation stream
+    /// * `width` - the width of the area
+    /// * `height` - the height of the area
+    fn collapse(
+        &self,
+        rng: &mut RandomNumberGenerator,
+        width: i32,
+        height: i32,
+    ) -> Option<Vec<bool>> {
+        if self.tiles.is_empty() {
+            return None;
+        }
+        // each cell is the top left corner of a pattern that must fit inside the area
+        let grid = Grid {
+            width: width - PATTERN_SIZE + 1,
+            height: height - PATTERN_SIZE + 1,
+            words: self.words,
+        };
+        let mut all = vec![u64::MAX; self.words];
+        let spare_bits = self.words * 64 - self.tiles.len();
+        all[self.words - 1] >>= spare_bits;
+        let mut wave: Vec<u64> = all.repeat((grid.width * grid.height) as usize);
+        let mut history: VecDeque<(Vec<u64>, usize, usize)> = VecDeque::new();
+
+        while let Some(cell) = self.most_constrained(rng, &grid, &wave) {
+            let pattern = self.choose(rng, grid.cell(&wave, cell));
+            if history.len() == MAX_SNAPSHOTS {
+                history.pop_front();
+            }
+            history.push_back((wave.clone(), cell, pattern));
+            let set = grid.cell_mut(&mut wave, cell);
+            set.iter_mut().for_each(|word| *word = 0);
+            set[pattern / 64] |= 1 << (pattern % 64);
+
+            let mut consistent = self.propagate(&grid, &mut wave, cell);
+            // undo choices, most recent first, ruling each one out as it goes
+            while !consistent {
+                let (snapshot, cell, pattern) = history.pop_back()?;
+                wave = snapshot;
+                let set = grid.cell_mut(&mut wave, cell);
+                set[pattern / 64] &= !(1 << (pattern % 64));
+                consistent =
+                    set.iter().any(|word| *word != 0) && self.propagate(&grid, &mut wave, cell);
+            }
+        }
+
+        // every cell has one pattern left, which paints the tiles it covers
+        let mut floor = vec![false; (width * height) as usize];
+        for y in 0..height {
+            for x in 0..width {
+                let (cx, cy) = (x.min(grid.width - 1), y.min(grid.height - 1));
+                let set = grid.cell(&wave, (cy * grid.width + cx) as usize);
+                let pattern = (0..self.tiles.len())
+                    .find(|p| set[p / 64] & (1 << (p % 64)) != 0)
+                    .unwrap();
+                let (px, py) = (x - cx, y - cy);
+                floor[(y * width + x) as usize] =
+                    self.tiles[pattern][(py * PATTERN_SIZE + px) as usize];
+            }
+        }
+        Some(floor)
+    }
+
+    /// Picks one of the undecided cells with the fewest patterns left, or
+    /// `None` once every cell is decided.
+    fn most_constrained(
+        &self,
+        rng: &mut RandomNumberGenerator,
+        grid: &Grid,
+        wave: &[u64],
+    ) -> Option<usize> {
+        let mut fewest = u32::MAX;
+        let mut cells = Vec::new();
+        for cell in 0..(grid.width * grid.height) as usize {
+            let count: u32 = grid.cell(wave, cell).iter().map(|w| w.count_ones()).sum();
+            if count <= 1 || count > fewest {
+                continue;
+            }
+            if count < fewest {
+                fewest = count;
+                cells.clear();
+            }
+            cells.push(cell);
+        }
+        rng.random_slice_entry(&cells).copied()
+    }
+
+    /// Picks one of the patterns left in a cell, weighted by how often it appears in the samples.
+    fn choose(&self, rng: &mut RandomNumberGenerator, set: &[u64]) -> usize {
+        let possible: Vec<usize> = (0..self.tiles.len())
+            .filter(|p| set[p / 64] & (1 << (p % 64)) != 0)
+            .collect();
+        let total: i32 = possible.iter().map(|p| self.weights[*p]).sum();
+        let mut roll = rng.range(0, total);
+        *possible
+            .iter()
+            .find(|p| {
+                roll -= self.weights[**p];
+                roll < 0
+            })
+            .unwrap()
+    }
+
+    /// Rules out patterns that no longer fit next to their neighbours, spreading
+    /// out from a changed cell. Returns false if a cell is left with none.
+    fn propagate(&self, grid: &Grid, wave: &mut [u64], changed: usize) -> bool {
+        let mut pending = vec![changed];
+        let mut allowed = vec![0u64; self.words];
+        while let Some(cell) = pending.pop() {
+            let (x, y) = (cell as i32 % grid.width, cell as i32 / grid.width);
+            for (direction, (dx, dy)) in DIRECTIONS.iter().enumerate() {
+                let (nx, ny) = (x + dx, y + dy);
+                if nx < 0 || ny < 0 || nx >= grid.width || ny >= grid.height {
+                    continue;
+                }
+                // the patterns any of this cell's patterns allow next to it
+                allowed.iter_mut().for_each(|word| *word = 0);
+                let set = grid.cell(wave, cell);
+                for p in (0..self.tiles.len()).filter(|p| set[p / 64] & (1 << (p % 64)) != 0) {
+                    allowed
+                        .iter_mut()
+                        .zip(self.compatible[direction][p].iter())
+                        .for_each(|(a, c)| *a |= c);
+                }
+
+                let neighbour = (ny * grid.width + nx) as usize;
+                let set = grid.cell_mut(wave, neighbour);
+                let mut changed = false;
+                set.iter_mut().zip(allowed.iter()).for_each(|(word, a)| {
+                    if *word & !a != 0 {
+                        *word &= a;
+                        changed = true;
+                    }
+                });
+                if changed {
+                    if set.iter().all(|word| *word == 0) {
+                        return false;
+                    }
+                    pending.push(neighbour);
+                }
+            }
+        }
+        true
+    }
+}
+
+/// The cells of the wave, each a bit set of the patterns it could still be.
+struct Grid {
+    width: i32,
+    height: i32,
+    words: usize,
+}
+
+impl Grid {
+    fn cell<'a>(&self, wave: &'a [u64], cell: usize) -> &'a [u64] {
+        &wave[cell * self.words..(cell + 1) * self.words]
+    }
+
+    fn cell_mut<'a>(&self, wave: &'a mut [u64], cell: usize) -> &'a mut [u64] {
+        &mut wave[cell * self.words..(cell + 1) * self.words]
+    }
+}
@@ -0,0 +1,156 @@
+use crate::prelude::*;
+
+/// How many tiles the arrow keys scroll the map by.
+const SCROLL_STEP: i32 = 2;
+
+/// Plays back the snapshots of a recorded level, see `MapBuilder::recorded`,
+/// one phase of generation at a time, drawn by the game's own map renderer.
+pub struct MapGenViewer {
+    snapshots: Vec<MapSnapshot>,
+    /// The index of the snapshot on screen.
+    current: usize,
+    paused: bool,
+    steps_per_second: f32,
+    /// The time since the last snapshot was shown.
+    elapsed_ms: f32,
+    /// The tile the camera is centred on.
+    focus: Point,
+    /// Holds a player that can see the whole map, as the map renderer expects.
+    ecs: World,
+    resources: Resources,
+    render_systems: Schedule,
+}
+
+impl MapGenViewer {
+    /// Prepares a recorded level for playback.
+    /// * `mb` - the level, built with `MapBuilder::recorded`
+    /// * `steps_per_second` - the playback speed
+    pub fn new(mb: MapBuilder, steps_per_second: f32) -> Self {
+        let mut snapshots = mb.snapshots.unwrap_or_default();
+        if snapshots.is_empty() {
+            snapshots.push(MapSnapshot {
+                label: "finished".to_string(),
+                map: mb.map.clone(),
+            });
+        }
+        let mut fov = FieldOfView::new(0);
+        fov.visible_tiles = (0..mb.map.height)
+            .flat_map(|y| (0..mb.map.width).map(move |x| Point::new(x, y)))
+            .collect();
+        let mut ecs = World::default();
+        ecs.push((Player { map_level: 0 }, fov));
+        let mut resources = Resources::default();
+        resources.insert(mb.theme);
+        let focus = Point::new(mb.map.width / 2, mb.map.height / 2);
+        Self {
+            snapshots,
+            current: 0,
+            paused: false,
+            steps_per_second,
+            elapsed_ms: 0.0,
+            focus,
+            ecs,
+            resources,
+            render_systems: build_map_render_scheduler(),
+        }
+    }
+
+    /// Handles the playback controls, and moves on to the next snapshot when it is due.
+    /// * `ctx` - the running `BTerm`, for the key pressed and the frame time
+    fn update(&mut self, ctx: &BTerm) {
+        let last = self.snapshots.len() - 1;
+        let map = &self.snapshots[self.current].map;
+        match ctx.key {
+            Some(VirtualKeyCode::P) => self.paused = !self.paused,
+            Some(VirtualKeyCode::N) => self.current = usize::min(self.current + 1, last),
+            Some(VirtualKeyCode::B) => self.current = self.current.saturating_sub(1),
+            Some(VirtualKeyCode::Home) => self.current = 0,
+            Some(VirtualKeyCode::End) => self.current = last,
+            Some(VirtualKeyCode::Plus | VirtualKeyCode::Equals | VirtualKeyCode::NumpadAdd) => {
+                self.steps_per_second = f32::min(self.steps_per_second * 2.0, 64.0)
+            }
+            Some(VirtualKeyCode::Minus | VirtualKeyCode::NumpadSubtract) => {
+                self.steps_per_second = f32::max(self.steps_per_second / 2.0, 0.25)
+            }
+            Some(VirtualKeyCode::Left) => self.focus.x -= SCROLL_STEP,
+            Some(VirtualKeyCode::Right) => self.focus.x += SCROLL_STEP,
+            Some(VirtualKeyCode::Up) => self.focus.y -= SCROLL_STEP,
+            Some(VirtualKeyCode::Down) => self.focus.y += SCROLL_STEP,
+            _ => {}
+        }
+        self.focus.x = self.focus.x.clamp(0, map.width - 1);
+        self.focus.y = self.focus.y.clamp(0, map.height - 1);
+
+        self.elapsed_ms += ctx.frame_time_ms;
+        if !self.paused && self.elapsed_ms >= 1000.0 / self.steps_per_second {
+            self.elapsed_ms = 0.0;
+            self.current = usize::min(self.current + 1, last);
+        }
+    }
+
+    /// Draws the snapshot on screen, with the playback status along the bottom.
+    /// * `ctx` - the running `BTerm`
+    fn render(&mut self, ctx: &mut BTerm) {
+        let snapshot = &self.snapshots[self.current];
+        self.resources.insert(snapshot.map.clone());
+        self.resources.insert(Camera::new(self.focus));
+        self.render_systems
+            .execute(&mut self.ecs, &mut self.resources);
+
+        ctx.set_active_console(2);
+        let status = if self.current == self.snapshots.len() - 1 {
+            "finished"
+        } else if self.paused {
+            "paused"
+        } else {
+            "playing"
+        };
+        ctx.print_color_centered(
+            SCREEN_HEIGHT * 2 - 3,
+            YELLOW,
+            BLACK,
+            format!(
+                "Step {} of {}: {}",
+                self.current + 1,
+                self.snapshots.len(),
+                snapshot.label
+            ),
+        );
+        ctx.print_color_centered(
+            SCREEN_HEIGHT * 2 - 2,
+            YELLOW,
+            BLACK,
+            format!("{} at {} steps/s", status, self.steps_per_second),
+        );
+        ctx.print_color_centered(
+            SCREEN_HEIGHT * 2 - 1,
+            WHITE,
+            BLACK,
+            "P pause, N/B step, Home/End jump, +/- speed, arrows scroll, Esc quit",
+        );
+    }
+}
+
+impl GameState for MapGenViewer {
+    /// Plays the snapshots back until the window is closed or Escape is pressed.
+    /// * `ctx` - allows access to change the currently running `bracket_terminal`
+    fn tick(&mut self, ctx: &mut BTerm) {
+        for console in 0..3 {
+            ctx.set_active_console(console);
+            ctx.cls();
+        }
+        let mut close_requested = false;
+        while let Some(event) = INPUT.lock().pop() {
+            if let BEvent::CloseRequested = event {
+                close_requested = true;
+            }
+        }
+        if close_requested || ctx.key == Some(VirtualKeyCode::Escape) {
+            ctx.quit();
+            return;
+        }
+        self.update(ctx);
+        self.render(ctx);
+        render_draw_buffer(ctx).expect("Render error");
+    }
+}
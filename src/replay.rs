@@ -0,0 +1,184 @@
+use crate::prelude::*;
+use ron::ser::PrettyConfig;
+use std::error::Error;
+use std::fs;
+
+/// The file the replay of the current game is written to.
+pub const REPLAY_FILE: &str = "replay.ron";
+
+/// A key pressed while the game was awaiting input.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReplayInput {
+    /// The turn the key was pressed on, starting at 0.
+    pub turn: usize,
+    /// The script name of the key, see `key_from_name`.
+    pub key: String,
+}
+
+/// Every key fed to the `player_input` system during a game, together with the
+/// seed, which is all that is needed to play the game again.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Replay {
+    /// The seed the game was started with.
+    pub seed: u64,
+    /// The width of the game's maps.
+    pub map_width: i32,
+    /// The height of the game's maps.
+    pub map_height: i32,
+    /// The keys pressed, one per turn.
+    pub inputs: Vec<ReplayInput>,
+}
+
+impl Replay {
+    /// Starts an empty recording of a game.
+    /// * `seed` - the seed the game was started with
+    /// * `map_width` - the width of the game's maps
+    /// * `map_height` - the height of the game's maps
+    pub fn new(seed: u64, map_width: i32, map_height: i32) -> Self {
+        Self {
+            seed,
+            map_width,
+            map_height,
+            inputs: Vec::new(),
+        }
+    }
+
+    /// Records the key pressed on the next turn. Keys the game has no use for
+    /// all skip the turn, so they are recorded as `Space`.
+    /// * `key` - the key fed to the `player_input` system
+    pub fn record(&mut self, key: VirtualKeyCode) {
+        self.inputs.push(ReplayInput {
+            turn: self.inputs.len(),
+            key: key_name(key).unwrap_or("Space").to_string(),
+        });
+    }
+
+    /// Reads a replay file.
+    /// * `path` - the replay file
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(ron::de::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// Writes the replay to a file, one key per line.
+    /// * `path` - the replay file
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        let config = PrettyConfig::new().with_depth_limit(2);
+        fs::write(path, ron::ser::to_string_pretty(self, config)?)?;
+        Ok(())
+    }
+}
+
+/// Feeds the keys of a `Replay` back into the game, with controls to pause,
+/// step turn by turn and change the speed.
+pub struct ReplayPlayer {
+    replay: Replay,
+    /// The index of the next input to feed to the game.
+    next: usize,
+    paused: bool,
+    turns_per_second: f32,
+    /// The time since the last input was fed to the game.
+    elapsed_ms: f32,
+}
+
+impl ReplayPlayer {
+    /// Prepares a replay for playback.
+    /// * `replay` - the recorded game
+    /// * `turns_per_second` - the playback speed
+    pub fn new(replay: Replay, turns_per_second: f32) -> Self {
+        Self {
+            replay,
+            next: 0,
+            paused: false,
+            turns_per_second,
+            elapsed_ms: 0.0,
+        }
+    }
+
+    /// Starts the recorded game again, ready for playback.
+    /// * `data` - the templates and vaults, which must match the ones recorded with
+    pub fn new_game(&self, data: GameData) -> Game {
+        Game::with_map_size(
+            data,
+            Some(self.replay.seed),
+            self.replay.map_width,
+            self.replay.map_height,
+        )
+    }
+
+    /// Handles the playback controls, and returns the recorded key to feed to
+    /// the game this tick, if one is due.
+    /// * `ctx` - the running `BTerm`, for the key pressed and the frame time
+    /// * `turn` - the number of turns the game has taken so far
+    /// * `awaiting_input` - true if the game is ready for the next key
+    /// * `log` - the game's log, told if the replay has fallen out of step with the game
+    pub fn next_key(
+        &mut self,
+        ctx: &BTerm,
+        turn: usize,
+        awaiting_input: bool,
+        log: &mut GameLog,
+    ) -> Option<VirtualKeyCode> {
+        let mut step = false;
+        match ctx.key {
+            Some(VirtualKeyCode::P) => self.paused = !self.paused,
+            Some(VirtualKeyCode::N) => step = true,
+            Some(VirtualKeyCode::Plus | VirtualKeyCode::Equals | VirtualKeyCode::NumpadAdd) => {
+                self.turns_per_second = f32::min(self.turns_per_second * 2.0, 64.0)
+            }
+            Some(VirtualKeyCode::Minus | VirtualKeyCode::NumpadSubtract) => {
+                self.turns_per_second = f32::max(self.turns_per_second / 2.0, 0.25)
+            }
+            _ => {}
+        }
+        self.elapsed_ms += ctx.frame_time_ms;
+
+        let due = step || (!self.paused && self.elapsed_ms >= 1000.0 / self.turns_per_second);
+        if !awaiting_input || !due {
+            return None;
+        }
+        self.elapsed_ms = 0.0;
+        let input = self.replay.inputs.get(self.next)?;
+        if input.turn != turn {
+            log.add(
+                format!(
+                    "Replay out of step: input {} was recorded on turn {}, but the game is on turn {}",
+                    self.next, input.turn, turn
+                ),
+                RED,
+            );
+        }
+        self.next += 1;
+        key_from_name(&input.key)
+    }
+
+    /// Draws the playback status along the bottom of the screen.
+    /// * `ctx` - the running `BTerm`
+    pub fn render(&self, ctx: &mut BTerm) {
+        ctx.set_active_console(2);
+        let status = if self.next >= self.replay.inputs.len() {
+            "finished"
+        } else if self.paused {
+            "paused"
+        } else {
+            "playing"
+        };
+        ctx.print_color_centered(
+            SCREEN_HEIGHT * 2 - 2,
+            YELLOW,
+            BLACK,
+            format!(
+                "Replay {}: turn {} of {} at {} turns/s",
+                status,
+                self.next,
+                self.replay.inputs.len(),
+                self.turns_per_second
+            ),
+        );
+        ctx.print_color_centered(
+            SCREEN_HEIGHT * 2 - 1,
+            WHITE,
+            BLACK,
+            "[P] pause  [N] next turn  [+/-] speed  [ESC] quit",
+        );
+    }
+}
@@ -0,0 +1,213 @@
+use crate::prelude::*;
+use legion::serialize::Canon;
+use serde::de::{self, DeserializeSeed, SeqAccess, Visitor};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// The file a game in progress is saved to.
+const SAVE_FILE: &str = "savegame.ron";
+
+/// The resources saved alongside the `World`.
+#[derive(Serialize, Deserialize)]
+struct SavedResources {
+    map: Map,
+    camera: Camera,
+    turn_state: TurnState,
+    /// The name of the level's `MapTheme`, see `Themes::by_name`.
+    theme: String,
+    rng: GameRng,
+    /// The keys pressed so far, so a continued game can still be replayed.
+    replay: Replay,
+    log: GameLog,
+}
+
+/// Registers every component that can appear in the `World` with a stable name.
+fn registry() -> Registry<String, Canon> {
+    let mut registry = Registry::<String>::default();
+    // message entities are pushed as `((), message)` tuples
+    registry.register::<()>("Message".to_string());
+    registry.register::<Point>("Point".to_string());
+    registry.register::<Render>("Render".to_string());
+    registry.register::<Player>("Player".to_string());
+    registry.register::<Enemy>("Enemy".to_string());
+    registry.register::<MovingRandomly>("MovingRandomly".to_string());
+    registry.register::<WantsToMove>("WantsToMove".to_string());
+    registry.register::<WantsToOperateDoor>("WantsToOperateDoor".to_string());
+    registry.register::<Health>("Health".to_string());
+    registry.register::<Name>("Name".to_string());
+    registry.register::<WantsToAttack>("WantsToAttack".to_string());
+    registry.register::<ChasingPlayer>("ChasingPlayer".to_string());
+    registry.register::<Item>("Item".to_string());
+    registry.register::<AmuletOfYala>("AmuletOfYala".to_string());
+    registry.register::<FieldOfView>("FieldOfView".to_string());
+    registry.register::<ProvidesHealing>("ProvidesHealing".to_string());
+    registry.register::<ProvidesDungeonMap>("ProvidesDungeonMap".to_string());
+    registry.register::<Carried>("Carried".to_string());
+    registry.register::<ActivateItem>("ActivateItem".to_string());
+    registry.register::<Damage>("Damage".to_string());
+    registry.register::<Weapon>("Weapon".to_string());
+    registry
+}
+
+/// Returns true if there is a saved game that can be continued.
+pub fn has_save() -> bool {
+    Path::new(SAVE_FILE).exists()
+}
+
+/// Deletes the saved game, if there is one.
+pub fn delete_save() -> io::Result<()> {
+    if has_save() {
+        fs::remove_file(SAVE_FILE)?;
+    }
+    Ok(())
+}
+
+/// Writes the game in progress to the save file.
+/// * `ecs` - the `World` holding every entity and component
+/// * `resources` - the resources describing the current level
+pub fn save_game(ecs: &World, resources: &Resources) -> Result<(), Box<dyn Error>> {
+    fs::write(SAVE_FILE, save_to_string(ecs, resources)?)?;
+    Ok(())
+}
+
+/// Reads the saved game back into a fresh `World` and `Resources`.
+/// * `themes` - the themes the level's theme is looked up in
+pub fn load_game(themes: &Themes) -> Result<(World, Resources), Box<dyn Error>> {
+    load_from_str(&fs::read_to_string(SAVE_FILE)?, themes)
+}
+
+// Writes the world and the resources of the current level as the text of a save file.
+fn save_to_string(ecs: &World, resources: &Resources) -> Result<String, Box<dyn Error>> {
+    let saved = SavedResources {
+        map: resources.get::<Map>().ok_or("No map to save")?.clone(),
+        camera: *resources.get::<Camera>().ok_or("No camera to save")?,
+        turn_state: *resources
+            .get::<TurnState>()
+            .ok_or("No turn state to save")?,
+        theme: resources
+            .get::<Box<dyn MapTheme>>()
+            .ok_or("No theme to save")?
+            .name()
+            .to_string(),
+        rng: resources.get::<GameRng>().ok_or("No rng to save")?.clone(),
+        replay: resources
+            .get::<Replay>()
+            .ok_or("No replay to save")?
+            .clone(),
+        log: resources.get::<GameLog>().ok_or("No log to save")?.clone(),
+    };
+    let registry = registry();
+    let world = ecs.as_serializable(any(), &registry);
+    Ok(ron::ser::to_string(&(saved, world))?)
+}
+
+// Reads the text of a save file back into a fresh world and resources.
+fn load_from_str(text: &str, themes: &Themes) -> Result<(World, Resources), Box<dyn Error>> {
+    let registry = registry();
+    let mut deserializer = ron::de::Deserializer::from_str(text)?;
+    let (saved, ecs) = SaveGameSeed {
+        registry: &registry,
+    }
+    .deserialize(&mut deserializer)?;
+
+    let mut resources = Resources::default();
+    resources.insert(saved.map);
+    resources.insert(saved.camera);
+    resources.insert(saved.turn_state);
+    let theme = themes
+        .by_name(&saved.theme)
+        .ok_or(format!("Unknown theme {}", saved.theme))?;
+    resources.insert(theme);
+    resources.insert(saved.rng);
+    resources.insert(saved.replay);
+    resources.insert(saved.log);
+    Ok((ecs, resources))
+}
+
+// The world can only be deserialized through the registry, so the saved
+// `(SavedResources, World)` tuple is read back with a seed.
+struct SaveGameSeed<'a> {
+    registry: &'a Registry<String, Canon>,
+}
+
+impl<'de, 'a> DeserializeSeed<'de> for SaveGameSeed<'a> {
+    type Value = (SavedResources, World);
+
+    fn deserialize<D: de::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_tuple(2, self)
+    }
+}
+
+impl<'de, 'a> Visitor<'de> for SaveGameSeed<'a> {
+    type Value = (SavedResources, World);
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a saved game")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let saved = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let ecs = seq
+            .next_element_seed(self.registry.as_deserialize())?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        Ok((saved, ecs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_game_loads_back_unchanged() {
+        let data = GameData::load().unwrap();
+        let mut game = Game::new(data.clone(), Some(99));
+        for key in [
+            VirtualKeyCode::Right,
+            VirtualKeyCode::Down,
+            VirtualKeyCode::Left,
+        ] {
+            while game.turn_state() != TurnState::AwaitingInput {
+                game.step(None);
+            }
+            game.step(Some(key));
+        }
+
+        let text = save_to_string(&game.ecs, &game.resources).unwrap();
+        let (ecs, resources) = load_from_str(&text, &data.themes).unwrap();
+
+        let saved_map = game.resources.get::<Map>().unwrap();
+        let loaded_map = resources.get::<Map>().unwrap();
+        assert_eq!(loaded_map.tiles, saved_map.tiles);
+        assert_eq!(loaded_map.revealed_tiles, saved_map.revealed_tiles);
+        assert_eq!(resources.get::<GameRng>().unwrap().seed, 99);
+        assert_eq!(resources.get::<Replay>().unwrap().inputs.len(), 3);
+        let player = |ecs: &World| {
+            <(&Point, &Health)>::query()
+                .filter(component::<Player>())
+                .iter(ecs)
+                .map(|(pos, health)| (*pos, *health))
+                .next()
+                .unwrap()
+        };
+        assert_eq!(player(&ecs), player(&game.ecs));
+        let named = |ecs: &World| {
+            let mut named: Vec<(i32, i32, String)> = <(&Point, &Name)>::query()
+                .iter(ecs)
+                .map(|(pos, name)| (pos.x, pos.y, name.0.clone()))
+                .collect();
+            named.sort();
+            named
+        };
+        assert_eq!(named(&ecs), named(&game.ecs));
+        assert_eq!(ecs.len(), game.ecs.len());
+    }
+}
@@ -1,6 +1,11 @@
 use crate::prelude::*;
 mod template;
-use template::Templates;
+mod watcher;
+pub use template::{
+    EntityType, Template, TemplateError, TemplateIssue, TemplateProblem, Templates, KNOWN_EFFECTS,
+    REGION_MONSTER_KINDS, TEMPLATE_FILE,
+};
+pub use watcher::TemplateWatcher;
 
 /// Adds the player and their components to the ECS.
 /// * `ecs` - provides mutable reference to the `World`
@@ -28,12 +33,15 @@ pub fn spawn_player(ecs: &mut World, pos: Point) {
 
 pub fn spawn_level(
     ecs: &mut World,
+    templates: &Templates,
     rng: &mut RandomNumberGenerator,
     level: usize,
     spawn_points: &[Point],
+    regions: &Regions,
+    entity_spawns: &[(Point, String)],
 ) {
-    let template = Templates::load();
-    template.spawn_entities(ecs, rng, level, spawn_points);
+    templates.spawn_entities(ecs, rng, level, spawn_points, regions);
+    templates.spawn_named(ecs, entity_spawns);
 }
 
 pub fn spawn_amulet_of_yala(ecs: &mut World, pos: Point) {
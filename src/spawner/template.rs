@@ -1,9 +1,20 @@
 use crate::prelude::*;
 use legion::systems::CommandBuffer;
-use ron::de::from_reader;
 use serde::Deserialize;
-use std::collections::HashSet;
-use std::fs::File;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+
+/// The file the game loads its entity templates from.
+pub const TEMPLATE_FILE: &str = "resources/template.ron";
+
+/// The effects a template can `provide`, and the components they become.
+pub const KNOWN_EFFECTS: [&str; 2] = ["Healing", "MagicMap"];
+
+/// The number of kinds of monster each region of a map spawns, see `MapBuilder::regions`.
+pub const REGION_MONSTER_KINDS: usize = 2;
 
 #[derive(Clone, Deserialize, Debug)]
 pub struct Template {
@@ -28,37 +39,300 @@ pub struct Templates {
     pub entities: Vec<Template>,
 }
 
+/// Something wrong with a single template, found by `Templates::validate`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TemplateProblem {
+    /// An enemy has no `hp`, so it could never be killed.
+    MissingHp,
+    /// An entry in `provides` that no component exists for.
+    UnknownEffect(String),
+    /// `levels` is empty, so the template would never be spawned.
+    NoLevels,
+    /// `frequency` is zero or less, so the template would never be spawned.
+    ZeroFrequency,
+    /// Another template already has this name.
+    DuplicateName,
+}
+
+impl fmt::Display for TemplateProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateProblem::MissingHp => write!(f, "enemies need an hp value"),
+            TemplateProblem::UnknownEffect(effect) => write!(
+                f,
+                "unknown effect {}, expected one of {}",
+                effect,
+                KNOWN_EFFECTS.join(", ")
+            ),
+            TemplateProblem::NoLevels => write!(f, "levels is empty"),
+            TemplateProblem::ZeroFrequency => write!(f, "frequency must be at least 1"),
+            TemplateProblem::DuplicateName => write!(f, "the name is used by another template"),
+        }
+    }
+}
+
+/// A `TemplateProblem` together with the template it was found in.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TemplateIssue {
+    /// The name of the template.
+    pub name: String,
+    /// The line the template's name is on, starting at 1, if it could be found.
+    pub line: Option<usize>,
+    pub problem: TemplateProblem,
+}
+
+impl fmt::Display for TemplateIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}: {}", line, self.name, self.problem),
+            None => write!(f, "{}: {}", self.name, self.problem),
+        }
+    }
+}
+
+/// Why the templates could not be loaded.
+#[derive(Debug)]
+pub enum TemplateError {
+    /// The file could not be read.
+    Io { path: String, error: io::Error },
+    /// The file is not valid RON, or does not describe `Templates`.
+    Parse {
+        path: String,
+        /// The line of the error, starting at 1, or 0 if it is not known.
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// The file parsed, but some templates are unusable.
+    Invalid {
+        path: String,
+        issues: Vec<TemplateIssue>,
+    },
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::Io { path, error } => write!(f, "{}: {}", path, error),
+            TemplateError::Parse {
+                path,
+                line,
+                column,
+                message,
+            } => {
+                if *line == 0 {
+                    write!(f, "{}: {}", path, message)
+                } else {
+                    write!(f, "{}:{}:{}: {}", path, line, column, message)
+                }
+            }
+            TemplateError::Invalid { path, issues } => {
+                write!(f, "{} has {} invalid template(s)", path, issues.len())?;
+                issues
+                    .iter()
+                    .try_for_each(|issue| write!(f, "\n  {}", issue))
+            }
+        }
+    }
+}
+
+impl Error for TemplateError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TemplateError::Io { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
 impl Templates {
-    pub fn load() -> Self {
-        let file = File::open("resources/template.ron").expect("Failed opening file");
-        from_reader(file).expect("Unable to load templates")
+    /// Reads and validates a template file.
+    /// * `path` - the template file, usually `TEMPLATE_FILE`
+    pub fn load(path: &str) -> Result<Self, TemplateError> {
+        let text = fs::read_to_string(path).map_err(|error| TemplateError::Io {
+            path: path.to_string(),
+            error,
+        })?;
+        Self::parse(&text, path)
+    }
+
+    /// Parses and validates the contents of a template file.
+    /// * `text` - the RON describing the templates
+    /// * `path` - the file the text came from, for error messages
+    pub fn parse(text: &str, path: &str) -> Result<Self, TemplateError> {
+        let templates = Self::parse_unvalidated(text, path)?;
+        let issues = templates.validate(text);
+        if issues.is_empty() {
+            Ok(templates)
+        } else {
+            Err(TemplateError::Invalid {
+                path: path.to_string(),
+                issues,
+            })
+        }
+    }
+
+    /// Parses the contents of a template file without validating it, for tools
+    /// that report every problem rather than stopping at the first.
+    /// * `text` - the RON describing the templates
+    /// * `path` - the file the text came from, for error messages
+    pub fn parse_unvalidated(text: &str, path: &str) -> Result<Self, TemplateError> {
+        ron::de::from_str(text).map_err(|e| TemplateError::Parse {
+            path: path.to_string(),
+            line: e.position.line,
+            column: e.position.col,
+            message: e.code.to_string(),
+        })
+    }
+
+    /// The templates that can spawn on a dungeon level.
+    /// * `level` - the dungeon level, starting at 0
+    pub fn available(&self, level: usize) -> impl Iterator<Item = &Template> {
+        self.entities
+            .iter()
+            .filter(move |e| e.levels.contains(&level))
+    }
+
+    /// Checks every template for mistakes that would break or silently skew
+    /// spawning, and returns what it finds.
+    /// * `text` - the RON the templates were parsed from, used to find the line of each problem
+    pub fn validate(&self, text: &str) -> Vec<TemplateIssue> {
+        let mut issues = Vec::new();
+        // how many templates with each name have been seen so far
+        let mut names = HashMap::new();
+        self.entities.iter().for_each(|template| {
+            let seen = names.entry(template.name.as_str()).or_insert(0);
+            *seen += 1;
+            let occurrence = *seen;
+            let mut report = |problem| {
+                issues.push(TemplateIssue {
+                    name: template.name.clone(),
+                    line: Self::line_of(text, &template.name, occurrence),
+                    problem,
+                })
+            };
+            if template.entity_type == EntityType::Enemy && template.hp.is_none() {
+                report(TemplateProblem::MissingHp);
+            }
+            template
+                .provides
+                .iter()
+                .flatten()
+                .filter(|(effect, _)| !KNOWN_EFFECTS.contains(&effect.as_str()))
+                .for_each(|(effect, _)| report(TemplateProblem::UnknownEffect(effect.clone())));
+            if template.levels.is_empty() {
+                report(TemplateProblem::NoLevels);
+            }
+            if template.frequency < 1 {
+                report(TemplateProblem::ZeroFrequency);
+            }
+            if occurrence > 1 {
+                report(TemplateProblem::DuplicateName);
+            }
+        });
+        issues
     }
 
+    // Finds the line naming the nth template called `name`, e.g. `name : "Orc",`.
+    fn line_of(text: &str, name: &str, occurrence: usize) -> Option<usize> {
+        let quoted = format!("\"{}\"", name);
+        text.lines()
+            .enumerate()
+            .filter(|(_, line)| line.contains("name") && line.contains(&quoted))
+            .nth(occurrence - 1)
+            .map(|(i, _)| i + 1)
+    }
+
+    /// Spawns a random entity, weighted by frequency, on each spawn point.
+    /// Spawn points in the same region share a population: every item, but
+    /// only `REGION_MONSTER_KINDS` kinds of monster chosen for that region.
+    /// * `ecs` - the `World` to spawn into
+    /// * `rng` - the level's spawn stream
+    /// * `level` - the dungeon level, starting at 0
+    /// * `spawn_points` - where to spawn
+    /// * `regions` - the region of each tile, see `MapBuilder::regions`
     pub fn spawn_entities(
         &self,
         ecs: &mut World,
         rng: &mut RandomNumberGenerator,
         level: usize,
         spawn_points: &[Point],
+        regions: &Regions,
     ) {
-        let mut available_entities = Vec::new();
-        self.entities
-            .iter()
-            .filter(|e| e.levels.contains(&level))
-            .for_each(|t| {
-                for _ in 0..t.frequency {
-                    available_entities.push(t);
-                }
-            });
+        let available: Vec<&Template> = self.available(level).collect();
+        let populations: Vec<Vec<&Template>> = (0..regions.count())
+            .map(|_| self.region_population(rng, &available))
+            .collect();
+        let available_entities = Self::weighted(&available);
+        let region_entities: Vec<Vec<&Template>> =
+            populations.iter().map(|p| Self::weighted(p)).collect();
+
         let mut commands = CommandBuffer::new(ecs);
         spawn_points.iter().for_each(|pt| {
-            if let Some(entity) = rng.random_slice_entry(&available_entities) {
+            let entities = regions
+                .region_at(*pt)
+                .map_or(&available_entities, |i| &region_entities[i]);
+            if let Some(entity) = rng.random_slice_entry(entities) {
                 self.spawn_entity(pt, entity, &mut commands);
             }
         });
         commands.flush(ecs);
     }
 
+    // Repeats each template as many times as its frequency, so picking one at
+    // random picks them in proportion.
+    fn weighted<'a>(templates: &[&'a Template]) -> Vec<&'a Template> {
+        let mut entities = Vec::new();
+        templates.iter().for_each(|t| {
+            for _ in 0..t.frequency {
+                entities.push(*t);
+            }
+        });
+        entities
+    }
+
+    // Chooses the templates one region spawns from: every item, and a few
+    // kinds of monster picked by frequency.
+    fn region_population<'a>(
+        &self,
+        rng: &mut RandomNumberGenerator,
+        available: &[&'a Template],
+    ) -> Vec<&'a Template> {
+        let (mut monsters, mut population): (Vec<&Template>, Vec<&Template>) = available
+            .iter()
+            .partition(|t| t.entity_type == EntityType::Enemy);
+        for _ in 0..REGION_MONSTER_KINDS {
+            let total: i32 = monsters.iter().map(|t| t.frequency).sum();
+            if total <= 0 {
+                break;
+            }
+            let mut roll = rng.range(0, total);
+            let chosen = monsters
+                .iter()
+                .position(|t| {
+                    roll -= t.frequency;
+                    roll < 0
+                })
+                .unwrap();
+            population.push(monsters.remove(chosen));
+        }
+        population
+    }
+
+    /// Spawns entities chosen by name rather than at random, e.g. by a vault.
+    /// Names with no template are skipped.
+    /// * `ecs` - the `World` to spawn into
+    /// * `entity_spawns` - the position and template name of each entity
+    pub fn spawn_named(&self, ecs: &mut World, entity_spawns: &[(Point, String)]) {
+        let mut commands = CommandBuffer::new(ecs);
+        entity_spawns.iter().for_each(|(pt, name)| {
+            if let Some(template) = self.entities.iter().find(|t| &t.name == name) {
+                self.spawn_entity(pt, template, &mut commands);
+            }
+        });
+        commands.flush(ecs);
+    }
+
     fn spawn_entity(
         &self,
         pt: &Point,
@@ -66,7 +340,7 @@ impl Templates {
         commands: &mut legion::systems::CommandBuffer,
     ) {
         let entity = commands.push((
-            pt.clone(),
+            *pt,
             Render {
                 color: ColorPair::new(WHITE, BLACK),
                 glyph: to_cp437(template.glyph),
@@ -80,11 +354,13 @@ impl Templates {
                 commands.add_component(entity, Enemy {});
                 commands.add_component(entity, FieldOfView::new(6));
                 commands.add_component(entity, ChasingPlayer {});
+                // validation guarantees enemies have hp
+                let hp = template.hp.unwrap_or(1);
                 commands.add_component(
                     entity,
                     Health {
-                        current: template.hp.unwrap(),
-                        max: template.hp.unwrap(),
+                        current: hp,
+                        max: hp,
                     },
                 );
             }
@@ -95,9 +371,8 @@ impl Templates {
                 .for_each(|(provides, n)| match provides.as_str() {
                     "Healing" => commands.add_component(entity, ProvidesHealing { amount: *n }),
                     "MagicMap" => commands.add_component(entity, ProvidesDungeonMap {}),
-                    _ => {
-                        println!("Warning: we don't know how to provide {}", provides);
-                    }
+                    // unknown effects are rejected by `validate`
+                    _ => {}
                 });
         }
         if let Some(damage) = &template.base_damage {
@@ -0,0 +1,47 @@
+use crate::prelude::*;
+use std::fs;
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often the template file is checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Watches a template file during development, reloading it whenever it is saved.
+pub struct TemplateWatcher {
+    path: String,
+    /// When the file was last modified, as of the last check.
+    modified: Option<SystemTime>,
+    last_poll: Instant,
+}
+
+impl TemplateWatcher {
+    /// Starts watching a template file, ignoring its current contents.
+    /// * `path` - the template file, usually `TEMPLATE_FILE`
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            modified: Self::modified(path),
+            last_poll: Instant::now(),
+        }
+    }
+
+    /// Returns the reloaded templates, or why they could not be loaded, if the
+    /// file has changed since the last call.
+    pub fn poll(&mut self) -> Option<Result<Templates, TemplateError>> {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return None;
+        }
+        self.last_poll = Instant::now();
+        let modified = Self::modified(&self.path);
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        Some(Templates::load(&self.path))
+    }
+
+    // The modification time of the file, or `None` while it can't be read,
+    // e.g. while an editor is replacing it.
+    fn modified(path: &str) -> Option<SystemTime> {
+        fs::metadata(path).and_then(|m| m.modified()).ok()
+    }
+}
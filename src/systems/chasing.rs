@@ -10,17 +10,17 @@ pub fn chasing(#[resource] map: &Map, ecs: &SubWorld, commands: &mut CommandBuff
     let mut movers = <(Entity, &Point, &ChasingPlayer, &FieldOfView)>::query();
     let mut positions = <(Entity, &Point, &Health)>::query();
     let mut player = <(&Point, &Player)>::query();
-    let player_pos = player.iter(ecs).nth(0).unwrap().0;
-    let player_idx = map_idx(player_pos.x, player_pos.y);
+    let player_pos = player.iter(ecs).next().unwrap().0;
+    let player_idx = map.map_idx(player_pos.x, player_pos.y);
 
     let search_targets = vec![player_idx];
-    let dijkstra_map = DijkstraMap::new(SCREEN_WIDTH, SCREEN_HEIGHT, &search_targets, map, 1024.0);
+    let dijkstra_map = DijkstraMap::new(map.width, map.height, &search_targets, map, 1024.0);
 
     movers.iter(ecs).for_each(|(entity, pos, _, fov)| {
-        if !fov.visible_tiles.contains(&player_pos) {
+        if !fov.visible_tiles.contains(player_pos) {
             return;
         }
-        let idx = map_idx(pos.x, pos.y);
+        let idx = map.map_idx(pos.x, pos.y);
         if let Some(destination) = DijkstraMap::find_lowest_exit(&dijkstra_map, idx, map) {
             let distance = DistanceAlg::Pythagoras.distance2d(*pos, *player_pos);
             let destination = if distance > 1.2 {
@@ -29,6 +29,19 @@ pub fn chasing(#[resource] map: &Map, ecs: &SubWorld, commands: &mut CommandBuff
                 *player_pos
             };
 
+            // the path runs through closed doors, which take a turn to open
+            if map.door_at(destination) == Some(false) {
+                commands.push((
+                    (),
+                    WantsToOperateDoor {
+                        entity: *entity,
+                        door: destination,
+                        open: true,
+                    },
+                ));
+                return;
+            }
+
             let mut attacked = false;
             positions
                 .iter(ecs)
@@ -6,7 +6,8 @@ use crate::prelude::*;
 #[write_component(Health)]
 #[read_component(Damage)]
 #[read_component(Carried)]
-pub fn combat(ecs: &mut SubWorld, commands: &mut CommandBuffer) {
+#[read_component(Name)]
+pub fn combat(ecs: &mut SubWorld, commands: &mut CommandBuffer, #[resource] log: &mut GameLog) {
     let mut attackers = <(Entity, &WantsToAttack)>::query();
     let victims: Vec<(Entity, Entity, Entity)> = attackers
         .iter(ecs)
@@ -37,17 +38,47 @@ pub fn combat(ecs: &mut SubWorld, commands: &mut CommandBuffer) {
             .sum();
 
         let final_damage = base_damage + weapon_damage;
+        let attacker_name = describe(ecs, *attacker);
+        let victim_name = describe(ecs, *victim);
 
-        if let Ok(mut health) = ecs
+        if let Ok(health) = ecs
             .entry_mut(*victim)
             .unwrap()
             .get_component_mut::<Health>()
         {
             health.current -= final_damage;
-            if health.current < 1 && !is_player {
-                commands.remove(*victim);
+            if is_player {
+                log.add(
+                    format!("The {} hits you for {}", attacker_name, final_damage),
+                    RED,
+                );
+                if health.current < 1 {
+                    log.add(format!("You are slain by the {}", attacker_name), RED);
+                }
+            } else {
+                log.add(
+                    format!("You hit the {} for {}", victim_name, final_damage),
+                    WHITE,
+                );
+                if health.current < 1 {
+                    log.add(format!("You kill the {}", victim_name), ORANGE);
+                    commands.remove(*victim);
+                }
             }
         }
         commands.remove(*message);
     });
 }
+
+// Names an entity for the log, e.g. "Orc".
+fn describe(ecs: &SubWorld, entity: Entity) -> String {
+    ecs.entry_ref(entity)
+        .ok()
+        .and_then(|entry| {
+            entry
+                .get_component::<Name>()
+                .ok()
+                .map(|name| name.0.clone())
+        })
+        .unwrap_or_else(|| "something".to_string())
+}
@@ -0,0 +1,123 @@
+use crate::prelude::*;
+
+#[system(for_each)]
+#[read_component(Point)]
+#[read_component(Player)]
+#[read_component(FieldOfView)]
+/// Opens or closes a door, unless something is standing in the doorway.
+/// * `entity` - the message entity, removed once it is processed
+/// * `want_door` - the door and what to do with it
+/// * `map` - access a mutable reference to the map, to change the door's tile
+/// * `log` - the game log, told what happened when the player uses a door
+pub fn doors(
+    entity: &Entity,
+    want_door: &WantsToOperateDoor,
+    #[resource] map: &mut Map,
+    #[resource] log: &mut GameLog,
+    ecs: &mut SubWorld,
+    commands: &mut CommandBuffer,
+) {
+    let by_player = ecs
+        .entry_ref(want_door.entity)
+        .is_ok_and(|entry| entry.get_component::<Player>().is_ok());
+    let blocked = <&Point>::query()
+        .iter(ecs)
+        .any(|pos| *pos == want_door.door);
+
+    // several monsters can try the same door in a turn, only the first changes it
+    if map.door_at(want_door.door) == Some(!want_door.open) {
+        if !want_door.open && blocked {
+            if by_player {
+                log.add("Something is in the way of the door", YELLOW);
+            }
+        } else {
+            let idx = map.map_idx(want_door.door.x, want_door.door.y);
+            map.tiles[idx] = if want_door.open {
+                TileType::OpenDoor
+            } else {
+                TileType::ClosedDoor
+            };
+            if by_player {
+                let action = if want_door.open { "open" } else { "close" };
+                log.add(format!("You {} the door", action), CYAN);
+            }
+            // whoever could see the door now sees more, or less, past it
+            <(Entity, &FieldOfView)>::query()
+                .iter(ecs)
+                .filter(|(_, fov)| fov.visible_tiles.contains(&want_door.door))
+                .for_each(|(viewer, fov)| commands.add_component(*viewer, fov.clone_dirty()));
+        }
+    }
+    // remove messages once they are processed
+    commands.remove(*entity);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOOR: Point = Point { x: 2, y: 2 };
+
+    // A small map of floor with a closed door in the middle, and a game log.
+    fn level() -> Resources {
+        let mut map = Map::new(5, 5);
+        let idx = map.map_idx(DOOR.x, DOOR.y);
+        map.tiles[idx] = TileType::ClosedDoor;
+        let mut resources = Resources::default();
+        resources.insert(map);
+        resources.insert(GameLog::new());
+        resources
+    }
+
+    // Has an entity standing at `pos` open or close the door, then runs the system.
+    fn operate(ecs: &mut World, resources: &mut Resources, pos: Point, open: bool) {
+        let entity = ecs.push((pos,));
+        ecs.push((
+            (),
+            WantsToOperateDoor {
+                entity,
+                door: DOOR,
+                open,
+            },
+        ));
+        Schedule::builder()
+            .add_system(doors_system())
+            .build()
+            .execute(ecs, resources);
+    }
+
+    #[test]
+    fn opening_and_closing_a_door_changes_sight_and_movement() {
+        let mut ecs = World::default();
+        let mut resources = level();
+        let idx = resources.get::<Map>().unwrap().map_idx(DOOR.x, DOOR.y);
+        {
+            let map = resources.get::<Map>().unwrap();
+            assert!(map.is_opaque(idx));
+            assert!(!map.can_enter_tile(DOOR));
+        }
+
+        operate(&mut ecs, &mut resources, Point::new(1, 2), true);
+        {
+            let map = resources.get::<Map>().unwrap();
+            assert_eq!(map.door_at(DOOR), Some(true));
+            assert!(!map.is_opaque(idx));
+            assert!(map.can_enter_tile(DOOR));
+        }
+
+        operate(&mut ecs, &mut resources, Point::new(1, 2), false);
+        let map = resources.get::<Map>().unwrap();
+        assert_eq!(map.door_at(DOOR), Some(false));
+        assert!(map.is_opaque(idx));
+        assert!(!map.can_enter_tile(DOOR));
+    }
+
+    #[test]
+    fn door_with_something_in_the_way_stays_open() {
+        let mut ecs = World::default();
+        let mut resources = level();
+        operate(&mut ecs, &mut resources, Point::new(1, 2), true);
+        operate(&mut ecs, &mut resources, DOOR, false);
+        assert_eq!(resources.get::<Map>().unwrap().door_at(DOOR), Some(true));
+    }
+}
@@ -10,7 +10,7 @@ use crate::prelude::*;
 pub fn end_turn(ecs: &SubWorld, #[resource] turn_state: &mut TurnState, #[resource] map: &Map) {
     let mut player_hp = <(&Health, &Point)>::query().filter(component::<Player>());
     let mut amulet = <&Point>::query().filter(component::<AmuletOfYala>());
-    let current_state = turn_state.clone();
+    let current_state = *turn_state;
     let mut new_state = match current_state {
         // if the game is awaiting input exit the function
         TurnState::AwaitingInput => return,
@@ -22,7 +22,7 @@ pub fn end_turn(ecs: &SubWorld, #[resource] turn_state: &mut TurnState, #[resour
     };
 
     let amulet_default = Point::new(-1, -1);
-    let amulet_pos = amulet.iter(ecs).nth(0).unwrap_or(&amulet_default);
+    let amulet_pos = amulet.iter(ecs).next().unwrap_or(&amulet_default);
 
     player_hp.iter(ecs).for_each(|(hp, pos)| {
         if hp.current < 1 {
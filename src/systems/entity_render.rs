@@ -5,22 +5,35 @@ use crate::prelude::*;
 #[read_component(Render)]
 #[read_component(FieldOfView)]
 #[read_component(Player)]
+#[read_component(Name)]
+#[allow(clippy::borrowed_box)]
 /// Renders entities with both a Point and Render component.
 /// * `ecs` - access to a SubWorld (like a World - but you can only see the components requested)
 /// * `camera` - gives access to the camera resource
-pub fn entity_render(#[resource] camera: &Camera, ecs: &SubWorld) {
-    let mut renderables = <(&Point, &Render)>::query();
+/// * `theme` - the level's theme, which may recolor entities by name
+pub fn entity_render(
+    #[resource] camera: &Camera,
+    #[resource] theme: &Box<dyn MapTheme>,
+    ecs: &SubWorld,
+) {
+    let mut renderables = <(&Point, &Render, Option<&Name>, Option<&Player>)>::query();
     let mut fov = <&FieldOfView>::query().filter(component::<Player>());
     let mut draw_batch = DrawBatch::new();
     draw_batch.target(1);
     let offset = Point::new(camera.left_x, camera.top_y);
-    let player_fov = fov.iter(ecs).nth(0).unwrap();
+    let player_fov = fov.iter(ecs).next().unwrap();
 
     renderables
         .iter(ecs)
-        .filter(|(pos, _)| player_fov.visible_tiles.contains(&pos))
-        .for_each(|(pos, render)| {
-            draw_batch.set(*pos - offset, render.color, render.glyph);
+        .filter(|(pos, ..)| player_fov.visible_tiles.contains(pos))
+        .for_each(|(pos, render, name, player)| {
+            let name = match (name, player) {
+                (_, Some(_)) => "Player",
+                (Some(name), None) => name.0.as_str(),
+                (None, None) => "",
+            };
+            let color = theme.entity_colors(name).unwrap_or(render.color);
+            draw_batch.set(*pos - offset, color, render.glyph);
         });
     draw_batch.submit(5000).expect("Batch error");
 }
@@ -6,13 +6,16 @@ use crate::prelude::*;
 #[read_component(Item)]
 #[read_component(Carried)]
 #[read_component(Name)]
-pub fn hud(ecs: &SubWorld) {
+pub fn hud(ecs: &SubWorld, #[resource] log: &GameLog) {
     let mut health_query = <&Health>::query().filter(component::<Player>());
-    let player_health = health_query.iter(ecs).nth(0).unwrap();
+    let player_health = health_query.iter(ecs).next().unwrap();
 
     let mut draw_batch = DrawBatch::new();
     draw_batch.target(2);
-    draw_batch.print_centered(1, "Explore the Dungeon, Cursor keys to move.");
+    draw_batch.print_centered(
+        1,
+        "Explore the Dungeon, Cursor keys to move, [L] for the message log.",
+    );
     draw_batch.bar_horizontal(
         Point::zero(),
         SCREEN_WIDTH * 2,
@@ -30,7 +33,8 @@ pub fn hud(ecs: &SubWorld) {
     );
     let (player, map_level) = <(Entity, &Player)>::query()
         .iter(ecs)
-        .find_map(|(entity, player)| Some((*entity, player.map_level)))
+        .map(|(entity, player)| (*entity, player.map_level))
+        .next()
         .unwrap();
 
     draw_batch.print_color_right(
@@ -55,5 +59,16 @@ pub fn hud(ecs: &SubWorld) {
             ColorPair::new(YELLOW, BLACK),
         );
     }
+
+    // the latest log entries, newest at the bottom, clear of the replay status lines
+    let recent = log.recent(HUD_LOG_LINES);
+    let top = SCREEN_HEIGHT * 2 - 3 - recent.len() as i32;
+    recent.iter().enumerate().for_each(|(i, entry)| {
+        draw_batch.print_color(
+            Point::new(1, top + i as i32),
+            &entry.text,
+            ColorPair::new(entry.color, BLACK),
+        );
+    });
     draw_batch.submit(10000).expect("Batch error");
 }
@@ -3,6 +3,7 @@ use crate::prelude::*;
 #[system]
 #[read_component(FieldOfView)]
 #[read_component(Player)]
+#[allow(clippy::borrowed_box)]
 /// Draws the map to the screen.
 /// * `map` - gives access to the map resource
 /// * `camera` - gives access to the camera resource
@@ -15,23 +16,20 @@ pub fn map_render(
     let mut fov = <&FieldOfView>::query().filter(component::<Player>());
     let mut draw_batch = DrawBatch::new();
     draw_batch.target(0);
-    let player_fov = fov.iter(ecs).nth(0).unwrap();
+    let player_fov = fov.iter(ecs).next().unwrap();
     // iterate through y and x values of the camera
     for y in camera.top_y..=camera.bottom_y {
         for x in camera.left_x..camera.right_x {
             let pt = Point::new(x, y);
             let offset = Point::new(camera.left_x, camera.top_y);
-            let idx = map_idx(x, y);
+            let idx = map.map_idx(x, y);
             if map.in_bounds(pt)
                 && (player_fov.visible_tiles.contains(&pt) | map.revealed_tiles[idx])
             {
-                let tint = if player_fov.visible_tiles.contains(&pt) {
-                    WHITE
-                } else {
-                    DARK_GREY
-                };
-                let glyph = theme.tile_to_render(map.tiles[idx]);
-                draw_batch.set(pt - offset, ColorPair::new(tint, BLACK), glyph);
+                let visible = player_fov.visible_tiles.contains(&pt);
+                let colors = theme.tile_colors(map.tiles[idx], visible);
+                let glyph = theme.tile_glyph(map.tiles[idx], pt);
+                draw_batch.set(pt - offset, colors, glyph);
             }
         }
     }
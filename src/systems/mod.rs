@@ -1,55 +1,51 @@
 use crate::prelude::*;
 
-mod chasing;
-mod combat;
-mod end_turn;
-mod entity_render;
-mod fov;
-mod hud;
-mod map_render;
-mod movement;
-mod player_input;
-mod random_move;
-mod tooltips;
-mod use_items;
+pub mod chasing;
+pub mod combat;
+pub mod doors;
+pub mod end_turn;
+pub mod entity_render;
+pub mod fov;
+pub mod hud;
+pub mod map_render;
+pub mod movement;
+pub mod player_input;
+pub mod random_move;
+pub mod tooltips;
+pub mod use_items;
 
 // flush is called when a system makes changes to the ECS dataset
 // the systems in each phase are divided by what makes sense:
 
-/// While awaiting input, the screen still needs to display the map and entities.
-/// It also calls the player_input system.
+// game logic and rendering are kept in separate schedules, so that the logic
+// can run without a window (see `headless`)
+
+/// While awaiting input, the game calls the player_input system.
 pub fn build_input_scheduler() -> Schedule {
     Schedule::builder()
         .add_system(player_input::player_input_system())
         .add_system(fov::fov_system())
-        .flush()
-        .add_system(map_render::map_render_system())
-        .add_system(entity_render::entity_render_system())
-        .add_system(hud::hud_system())
-        .add_system(tooltips::tooltips_system())
         .build()
 }
 
 /// When it is the player's turn, the game does not accept input-but does check
-/// for collisions, as well as rendering everything. It finishes with end_turn.
+/// for collisions. It finishes with end_turn.
 pub fn build_player_scheduler() -> Schedule {
     Schedule::builder()
         .add_system(use_items::use_items_system())
         .add_system(combat::combat_system())
         .flush()
         .add_system(movement::movement_system())
+        .add_system(doors::doors_system())
         .flush()
         .add_system(fov::fov_system())
         .flush()
-        .add_system(map_render::map_render_system())
-        .add_system(entity_render::entity_render_system())
-        .add_system(hud::hud_system())
         .add_system(end_turn::end_turn_system())
         .build()
 }
 
 /// When it is the monsters turn the game does not accept input-but does check
-/// for collisions, renders everything and adds random movements. It finishes with end_turn.
+/// for collisions and adds random movements. It finishes with end_turn.
 pub fn build_monster_scheduler() -> Schedule {
     Schedule::builder()
         .add_system(random_move::random_move_system())
@@ -59,12 +55,28 @@ pub fn build_monster_scheduler() -> Schedule {
         .add_system(combat::combat_system())
         .flush()
         .add_system(movement::movement_system())
+        .add_system(doors::doors_system())
         .flush()
         .add_system(fov::fov_system())
         .flush()
+        .add_system(end_turn::end_turn_system())
+        .build()
+}
+
+/// Whatever phase the turn is in, the screen still needs to display the map,
+/// entities and HUD. Only used when the game has a window to draw in.
+pub fn build_render_scheduler() -> Schedule {
+    Schedule::builder()
         .add_system(map_render::map_render_system())
         .add_system(entity_render::entity_render_system())
         .add_system(hud::hud_system())
-        .add_system(end_turn::end_turn_system())
+        .add_system(tooltips::tooltips_system())
+        .build()
+}
+
+/// Draws just the map, for watching a level being generated, see `MapGenViewer`.
+pub fn build_map_render_scheduler() -> Schedule {
+    Schedule::builder()
+        .add_system(map_render::map_render_system())
         .build()
 }
@@ -22,7 +22,8 @@ pub fn movement(
                     // update the players camera information
                     camera.on_player_move(want_move.destination);
                     fov.visible_tiles.iter().for_each(|pos| {
-                        map.revealed_tiles[map_idx(pos.x, pos.y)] = true;
+                        let idx = map.map_idx(pos.x, pos.y);
+                        map.revealed_tiles[idx] = true;
                     });
                 }
             }
@@ -8,6 +8,7 @@ use crate::prelude::*;
 #[read_component(Item)]
 #[read_component(Carried)]
 #[read_component(Weapon)]
+#[read_component(Name)]
 /// Handles the player input, allowing the player to control the character.
 /// * `ecs` - mutable access to a SubWorld (like a World - but you can only see the components requested)
 /// * `map` - access a read-only reference to the map
@@ -16,8 +17,10 @@ use crate::prelude::*;
 pub fn player_input(
     ecs: &mut SubWorld,
     commands: &mut CommandBuffer,
+    #[resource] map: &Map,
     #[resource] key: &Option<VirtualKeyCode>,
     #[resource] turn_state: &mut TurnState,
+    #[resource] log: &mut GameLog,
 ) {
     let mut players = <(Entity, &Point)>::query().filter(component::<Player>());
 
@@ -30,7 +33,8 @@ pub fn player_input(
             VirtualKeyCode::G => {
                 let (player, player_pos) = players
                     .iter(ecs)
-                    .find_map(|(entity, pos)| Some((*entity, *pos)))
+                    .map(|(entity, pos)| (*entity, *pos))
+                    .next()
                     .unwrap();
 
                 let mut items = <(Entity, &Item, &Point)>::query();
@@ -42,6 +46,9 @@ pub fn player_input(
                         commands.add_component(*entity, Carried(player));
 
                         if let Ok(e) = ecs.entry_ref(*entity) {
+                            if let Ok(name) = e.get_component::<Name>() {
+                                log.add(format!("You pick up the {}", name.0), CYAN);
+                            }
                             if e.get_component::<Weapon>().is_ok() {
                                 <(Entity, &Carried, &Weapon)>::query()
                                     .iter(ecs)
@@ -54,6 +61,13 @@ pub fn player_input(
                     });
                 Point::new(0, 0)
             }
+            VirtualKeyCode::O | VirtualKeyCode::C => {
+                // trying to use a door that isn't there costs no turn
+                if !operate_doors(key == VirtualKeyCode::O, ecs, map, commands, log) {
+                    return;
+                }
+                Point::zero()
+            }
             VirtualKeyCode::Key1 => use_item(0, ecs, commands),
             VirtualKeyCode::Key2 => use_item(1, ecs, commands),
             VirtualKeyCode::Key3 => use_item(2, ecs, commands),
@@ -68,7 +82,8 @@ pub fn player_input(
 
         let (player_entity, destination) = players
             .iter(ecs)
-            .find_map(|(entity, pos)| Some((*entity, *pos + delta)))
+            .map(|(entity, pos)| (*entity, *pos + delta))
+            .next()
             .unwrap();
         let mut enemies = <(Entity, &Point)>::query().filter(component::<Enemy>());
         let mut did_something = false;
@@ -88,7 +103,17 @@ pub fn player_input(
                         },
                     ));
                 });
-            if !hit_something {
+            if !hit_something && map.door_at(destination) == Some(false) {
+                // bumping into a closed door opens it
+                commands.push((
+                    (),
+                    WantsToOperateDoor {
+                        entity: player_entity,
+                        door: destination,
+                        open: true,
+                    },
+                ));
+            } else if !hit_something {
                 commands.push((
                     (),
                     WantsToMove {
@@ -102,10 +127,66 @@ pub fn player_input(
     }
 }
 
+/// Opens, or closes, every door next to the player. Returns false, after
+/// saying why in the log, if there is no door to open or close.
+/// * `open` - true to open closed doors, false to close open ones
+fn operate_doors(
+    open: bool,
+    ecs: &SubWorld,
+    map: &Map,
+    commands: &mut CommandBuffer,
+    log: &mut GameLog,
+) -> bool {
+    let (player_entity, player_pos) = <(Entity, &Point)>::query()
+        .filter(component::<Player>())
+        .iter(ecs)
+        .map(|(entity, pos)| (*entity, *pos))
+        .next()
+        .unwrap();
+
+    let doors: Vec<Point> = [
+        Point::new(-1, 0),
+        Point::new(1, 0),
+        Point::new(0, -1),
+        Point::new(0, 1),
+    ]
+    .iter()
+    .map(|delta| player_pos + *delta)
+    .filter(|pos| map.door_at(*pos) == Some(!open))
+    .collect();
+    if doors.is_empty() {
+        let action = if open { "open" } else { "close" };
+        log.add(format!("There is no door to {} here", action), YELLOW);
+        return false;
+    }
+
+    // a door can't be closed on anything standing or lying in the doorway
+    let free: Vec<Point> = doors
+        .into_iter()
+        .filter(|door| open || !<&Point>::query().iter(ecs).any(|pos| pos == door))
+        .collect();
+    if free.is_empty() {
+        log.add("Something is in the way of the door", YELLOW);
+        return false;
+    }
+    free.into_iter().for_each(|door| {
+        commands.push((
+            (),
+            WantsToOperateDoor {
+                entity: player_entity,
+                door,
+                open,
+            },
+        ));
+    });
+    true
+}
+
 fn use_item(n: usize, ecs: &mut SubWorld, commands: &mut CommandBuffer) -> Point {
     let player_entity = <(Entity, &Player)>::query()
         .iter(ecs)
-        .find_map(|(entity, _player)| Some(*entity))
+        .map(|(entity, _player)| *entity)
+        .next()
         .unwrap();
 
     let item_entity = <(Entity, &Item, &Carried)>::query()
@@ -113,7 +194,8 @@ fn use_item(n: usize, ecs: &mut SubWorld, commands: &mut CommandBuffer) -> Point
         .filter(|(_, _, carried)| carried.0 == player_entity)
         .enumerate()
         .filter(|(item_count, (_, _, _))| *item_count == n)
-        .find_map(|(_, (item_entity, _, _))| Some(*item_entity));
+        .map(|(_, (item_entity, _, _))| *item_entity)
+        .next();
 
     if let Some(item_entity) = item_entity {
         commands.push((
@@ -127,3 +209,59 @@ fn use_item(n: usize, ecs: &mut SubWorld, commands: &mut CommandBuffer) -> Point
 
     Point::zero()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOOR: Point = Point { x: 3, y: 2 };
+
+    // A floor map with an open door east of the player, after pressing `key`.
+    // Returns the turn state and the number of door messages pushed.
+    fn press(key: VirtualKeyCode, blocker: Option<Point>) -> (TurnState, usize) {
+        let mut map = Map::new(5, 5);
+        let idx = map.map_idx(DOOR.x, DOOR.y);
+        map.tiles[idx] = TileType::OpenDoor;
+        let mut ecs = World::default();
+        ecs.push((Player { map_level: 0 }, Point::new(2, 2)));
+        if let Some(pos) = blocker {
+            ecs.push((Item, pos));
+        }
+        let mut resources = Resources::default();
+        resources.insert(map);
+        resources.insert(Some(key));
+        resources.insert(TurnState::AwaitingInput);
+        resources.insert(GameLog::new());
+        Schedule::builder()
+            .add_system(player_input_system())
+            .build()
+            .execute(&mut ecs, &mut resources);
+
+        let turn_state = *resources.get::<TurnState>().unwrap();
+        (
+            turn_state,
+            <&WantsToOperateDoor>::query().iter(&ecs).count(),
+        )
+    }
+
+    #[test]
+    fn closing_a_door_ends_the_turn() {
+        assert_eq!(press(VirtualKeyCode::C, None), (TurnState::PlayerTurn, 1));
+    }
+
+    #[test]
+    fn no_door_to_use_costs_no_turn() {
+        assert_eq!(
+            press(VirtualKeyCode::O, None),
+            (TurnState::AwaitingInput, 0)
+        );
+    }
+
+    #[test]
+    fn blocked_door_costs_no_turn() {
+        assert_eq!(
+            press(VirtualKeyCode::C, Some(DOOR)),
+            (TurnState::AwaitingInput, 0)
+        );
+    }
+}
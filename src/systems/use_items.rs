@@ -5,19 +5,33 @@ use crate::prelude::*;
 #[read_component(ProvidesHealing)]
 #[write_component(Health)]
 #[read_component(ProvidesDungeonMap)]
-pub fn use_items(ecs: &mut SubWorld, commands: &mut CommandBuffer, #[resource] map: &mut Map) {
-    let mut healing_to_apply = Vec::<(Entity, i32)>::new();
+#[read_component(Name)]
+#[read_component(Player)]
+pub fn use_items(
+    ecs: &mut SubWorld,
+    commands: &mut CommandBuffer,
+    #[resource] map: &mut Map,
+    #[resource] log: &mut GameLog,
+) {
+    let mut healing_to_apply = Vec::<(Entity, i32, String)>::new();
     <(Entity, &ActivateItem)>::query()
         .iter(ecs)
         .for_each(|(entity, activate)| {
             let item = ecs.entry_ref(activate.item);
             if let Ok(item) = item {
+                let name = item
+                    .get_component::<Name>()
+                    .map_or("item".to_string(), |name| name.0.clone());
                 if let Ok(healing) = item.get_component::<ProvidesHealing>() {
-                    healing_to_apply.push((activate.used_by, healing.amount));
+                    healing_to_apply.push((activate.used_by, healing.amount, name.clone()));
                 }
 
                 if let Ok(_mapper) = item.get_component::<ProvidesDungeonMap>() {
                     map.revealed_tiles.iter_mut().for_each(|t| *t = true);
+                    log.add(
+                        format!("You read the {} and the level is revealed", name),
+                        CYAN,
+                    );
                 }
             }
 
@@ -27,8 +41,17 @@ pub fn use_items(ecs: &mut SubWorld, commands: &mut CommandBuffer, #[resource] m
 
     for heal in healing_to_apply.iter() {
         if let Ok(mut target) = ecs.entry_mut(heal.0) {
+            let is_player = target.get_component::<Player>().is_ok();
             if let Ok(health) = target.get_component_mut::<Health>() {
-                health.current = i32::min(health.max, health.current + heal.1);
+                let healed = i32::min(health.max, health.current + heal.1);
+                let recovered = healed - health.current;
+                health.current = healed;
+                if is_player {
+                    log.add(
+                        format!("You drink the {} and recover {} hp", heal.2, recovered),
+                        GREEN,
+                    );
+                }
             }
         }
     }
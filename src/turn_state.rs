@@ -1,4 +1,6 @@
-#[derive(Copy, Clone, Debug, PartialEq)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 /// Represents one of three turn states
 pub enum TurnState {
     AwaitingInput,
@@ -7,4 +9,6 @@ pub enum TurnState {
     GameOver,
     Victory,
     NextLevel,
+    /// Waiting for the player to continue the saved game or start a new one.
+    MainMenu,
 }